@@ -0,0 +1,132 @@
+//! Bounds-checked accesses to the contents of a mapping
+
+use std::mem::{align_of, size_of};
+use std::sync::atomic::{AtomicI32, AtomicU16, AtomicU32, AtomicU8, AtomicUsize};
+#[cfg(target_has_atomic = "64")]
+use std::sync::atomic::{AtomicI64, AtomicU64};
+
+use crate::{Protection, Shmem, ShmemError};
+
+/// Types that can be read from and written to shared memory as plain bytes
+///
+/// # Safety
+/// Every bit pattern of `size_of::<Self>()` bytes must be a valid value of the type, which rules out
+/// references, `bool`, `char`, most enums and types with padding.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(unsafe impl Pod for $t {})*
+    };
+}
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+macro_rules! atomic_at {
+    ($(#[$cfg:meta])* $name:ident, $atomic:ty) => {
+        /// Returns an atomic view of the value at `offset`, which must be aligned for it
+        ///
+        /// Atomic operations are coherent between every process that maps the same memory.
+        $(#[$cfg])*
+        pub fn $name(&self, offset: usize) -> Result<&$atomic, ShmemError> {
+            let ptr = self.checked_ptr(
+                offset,
+                size_of::<$atomic>(),
+                align_of::<$atomic>(),
+                Protection::ReadWrite,
+            )?;
+            // Safety: the value is aligned, lies within the mapping and lives as long as `self`
+            Ok(unsafe { &*(ptr as *const $atomic) })
+        }
+    };
+}
+
+impl Shmem {
+    /// Reads the value at `offset` with a volatile read
+    ///
+    /// `offset` must be aligned for `T`. Writes to the same bytes by other processes must be synchronized,
+    /// or the value read may be torn. Within this process, only the `atomic_*_at()` accessors can write
+    /// through a shared reference and they must not be used on the same bytes at the same time.
+    pub fn read_at<T: Pod>(&self, offset: usize) -> Result<T, ShmemError> {
+        let ptr = self.checked_ptr(
+            offset,
+            size_of::<T>(),
+            align_of::<T>(),
+            Protection::ReadOnly,
+        )?;
+        // Safety: the value is aligned, lies within the mapping and any bit pattern is valid for T
+        Ok(unsafe { (ptr as *const T).read_volatile() })
+    }
+
+    /// Writes `value` at `offset` with a volatile write
+    ///
+    /// `offset` must be aligned for `T`. Takes `&mut self` so the write cannot race with other accesses
+    /// from this process, writes from other processes must be synchronized like for `read_at()`.
+    pub fn write_at<T: Pod>(&mut self, offset: usize, value: T) -> Result<(), ShmemError> {
+        let ptr = self.checked_ptr(
+            offset,
+            size_of::<T>(),
+            align_of::<T>(),
+            Protection::ReadWrite,
+        )?;
+        // Safety: the value is aligned and lies within the mapping
+        unsafe { (ptr as *mut T).write_volatile(value) };
+        Ok(())
+    }
+
+    /// Copies `src` into the mapping, starting at `offset`
+    ///
+    /// The same synchronization rules as `write_at()` apply.
+    pub fn copy_from(&mut self, offset: usize, src: &[u8]) -> Result<(), ShmemError> {
+        let ptr = self.checked_ptr(offset, src.len(), 1, Protection::ReadWrite)?;
+        // Safety: the range lies within the mapping, which never overlaps with memory we own
+        unsafe { std::ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len()) };
+        Ok(())
+    }
+
+    /// Fills `dst` with the contents of the mapping, starting at `offset`
+    ///
+    /// The same synchronization rules as `read_at()` apply.
+    pub fn copy_to(&self, offset: usize, dst: &mut [u8]) -> Result<(), ShmemError> {
+        let ptr = self.checked_ptr(offset, dst.len(), 1, Protection::ReadOnly)?;
+        // Safety: the range lies within the mapping, which never overlaps with memory we own
+        unsafe { std::ptr::copy_nonoverlapping(ptr, dst.as_mut_ptr(), dst.len()) };
+        Ok(())
+    }
+
+    atomic_at!(atomic_u8_at, AtomicU8);
+    atomic_at!(atomic_u16_at, AtomicU16);
+    atomic_at!(atomic_u32_at, AtomicU32);
+    atomic_at!(atomic_i32_at, AtomicI32);
+    atomic_at!(
+        #[cfg(target_has_atomic = "64")]
+        atomic_u64_at,
+        AtomicU64
+    );
+    atomic_at!(
+        #[cfg(target_has_atomic = "64")]
+        atomic_i64_at,
+        AtomicI64
+    );
+    atomic_at!(atomic_usize_at, AtomicUsize);
+
+    /// Returns a pointer to `len` bytes at `offset` after making sure they can be accessed as requested
+    fn checked_ptr(
+        &self,
+        offset: usize,
+        len: usize,
+        align: usize,
+        access: Protection,
+    ) -> Result<*mut u8, ShmemError> {
+        self.check_range(offset, len)?;
+        // Safety: the offset lies within the mapping
+        let ptr = unsafe { self.as_ptr().add(offset) };
+        if ptr as usize % align != 0 {
+            return Err(ShmemError::UnalignedOffset);
+        }
+        if self.protected.restricts(offset..offset + len, access) {
+            return Err(ShmemError::RangeProtected);
+        }
+        Ok(ptr)
+    }
+}
@@ -0,0 +1,53 @@
+//! Giving the OS hints about how ranges of a mapping are used
+
+use std::ops::Range;
+
+use crate::{Shmem, ShmemError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Hints passed to `madvise()` about a range of a mapping
+///
+/// The descriptions below are about shared mappings. Some advice is only available on Linux and
+/// returns `ShmemError::Unsupported` elsewhere.
+pub enum Advice {
+    /// Pages will be accessed in order. The OS reads ahead aggressively and may drop pages soon after they
+    /// were accessed.
+    Sequential,
+    /// Pages will be accessed in no particular order. Read-ahead is disabled.
+    Random,
+    /// Pages will be accessed soon. The OS starts bringing them in from swap (or from the file in tmpfs
+    /// mode) in the background.
+    WillNeed,
+    /// Pages won't be accessed soon. They are only dropped from this process' page tables: unlike with
+    /// private mappings, their contents are preserved and later accesses fault the shared data back in.
+    /// No memory is freed, the pages stay in the shared memory object until it is removed (see `Remove`).
+    DontNeed,
+    /// Frees the pages and their backing store, punching a hole in the shared memory object. Every process
+    /// that maps the range reads zeros afterwards. (Linux only)
+    Remove,
+    /// Allows transparent huge pages for the range. For shared memory, this requires
+    /// `/sys/kernel/mm/transparent_hugepage/shmem_enabled` to be set to `advise`. (Linux only)
+    HugePage,
+    /// Excludes the range from core dumps of this process. (Linux only)
+    DontDump,
+    /// Does not map the range in child processes created with `fork()`. (Linux only)
+    DontFork,
+}
+
+impl Shmem {
+    /// Gives the OS a hint about how `range` of the mapping is going to be used
+    ///
+    /// The range follows the same alignment rules as `protect()`. Advice only applies to the current
+    /// process, except for `Remove` which affects the shared memory itself. On a mirrored mapping, the
+    /// advice is given for both views of the range.
+    pub fn advise(&self, range: Range<usize>, advice: Advice) -> Result<(), ShmemError> {
+        self.check_page_range(&range)?;
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let offset = self.config.data_offset();
+        self.mapping
+            .advise(offset + range.start, range.end - range.start, advice)
+    }
+}
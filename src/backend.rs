@@ -0,0 +1,240 @@
+//! Where the shared memory objects that get mapped come from
+
+use std::fs::OpenOptions;
+#[cfg(target_os = "linux")]
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(feature = "logging")]
+use std::os::unix::io::AsRawFd;
+
+use nix::fcntl::OFlag;
+use nix::sys::mman::{shm_open, shm_unlink};
+use nix::sys::stat::{fstat, FileStat, Mode};
+use nix::unistd::ftruncate;
+
+use crate::log::*;
+use crate::ShmemError;
+
+/// Information about an existing shared memory object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShmemInfo {
+    /// Size of the object in bytes
+    pub size: usize,
+    /// Permissions of the object
+    pub mode: Mode,
+}
+
+/// Creates, opens and deletes the named objects that `Shmem` maps into memory
+///
+/// Objects are handed over as file descriptors which are then mapped with `mmap()`. The crate uses
+/// `PosixShmBackend`, or `TmpfsBackend` in tmpfs mode, unless another backend is set with
+/// `ShmemConf::backend()`. In tmpfs mode, backends receive the path of the file as `unique_id`.
+pub trait ShmemBackend: Send + Sync {
+    /// Creates the object `unique_id` with a size of `size` bytes
+    ///
+    /// Must fail with `ShmemError::MappingIdExists` if the object already exists.
+    fn create(&self, unique_id: &str, size: usize, mode: Mode) -> Result<OwnedFd, ShmemError>;
+
+    /// Opens the existing object `unique_id` for reading and writing
+    fn open(&self, unique_id: &str) -> Result<OwnedFd, ShmemError>;
+
+    /// Whether `open()` finds objects by their `unique_id`
+    ///
+    /// Backends whose objects can only be shared by passing their file descriptor return `false`, in which
+    /// case `Shmem::is_stale()` fails with `ShmemError::Unsupported`.
+    fn can_open(&self) -> bool {
+        true
+    }
+
+    /// Deletes the object `unique_id`
+    ///
+    /// Processes that have the object mapped keep access to it. Deleting an object that does not exist
+    /// is not an error.
+    fn unlink(&self, unique_id: &str) -> Result<(), ShmemError>;
+
+    /// Returns the status of the existing object `unique_id`, as `fstat()` would
+    ///
+    /// The default implementation opens the object and closes it again. Outside of Linux, closing a
+    /// descriptor releases the locks our process holds on the object for `ShmemConf::owner_election()`
+    /// and channels, so backends that can look objects up without opening them should override this and
+    /// `stat_opens()`.
+    fn stat(&self, unique_id: &str) -> Result<FileStat, ShmemError> {
+        let fd = self.open(unique_id)?;
+        fstat(&fd).map_err(|e| ShmemError::MapOpenFailed(e as u32))
+    }
+
+    /// Whether `stat()` opens the object
+    fn stat_opens(&self) -> bool {
+        true
+    }
+
+    /// Returns information about the existing object `unique_id`
+    ///
+    /// This relies on `stat()`, with the same caveat about locks.
+    fn info(&self, unique_id: &str) -> Result<ShmemInfo, ShmemError> {
+        let stat = self.stat(unique_id)?;
+        Ok(ShmemInfo {
+            size: stat.st_size as usize,
+            mode: Mode::from_bits_truncate(stat.st_mode),
+        })
+    }
+}
+
+/// Sets the size of a newly created object, deleting it on failure
+fn truncate_new(
+    backend: &dyn ShmemBackend,
+    unique_id: &str,
+    fd: OwnedFd,
+    size: usize,
+) -> Result<OwnedFd, ShmemError> {
+    trace!("ftruncate({}, {})", fd.as_raw_fd(), size);
+    match ftruncate(&fd, size as _) {
+        Ok(_) => Ok(fd),
+        Err(e) => {
+            let _ = backend.unlink(unique_id);
+            Err(ShmemError::UnknownOsError(e as u32))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// POSIX shared memory objects (`shm_open()`)
+pub struct PosixShmBackend;
+
+impl ShmemBackend for PosixShmBackend {
+    fn create(&self, unique_id: &str, size: usize, mode: Mode) -> Result<OwnedFd, ShmemError> {
+        let flags = OFlag::O_CREAT | OFlag::O_EXCL | OFlag::O_RDWR; //create exclusively (error if collision) and read/write to allow resize
+        let fd = match shm_open(unique_id, flags, mode) {
+            Ok(v) => {
+                trace!(
+                    "shm_open({}, {:X}, {:X}) == {}",
+                    unique_id,
+                    flags,
+                    mode,
+                    v.as_raw_fd()
+                );
+                v
+            }
+            Err(nix::Error::EEXIST) => return Err(ShmemError::MappingIdExists),
+            Err(e) => return Err(ShmemError::MapCreateFailed(e as u32)),
+        };
+        truncate_new(self, unique_id, fd, size)
+    }
+
+    fn open(&self, unique_id: &str) -> Result<OwnedFd, ShmemError> {
+        match shm_open(unique_id, OFlag::O_RDWR, Mode::S_IRUSR) {
+            Ok(v) => {
+                trace!(
+                    "shm_open({}, {:X}, {:X}) == {}",
+                    unique_id,
+                    OFlag::O_RDWR,
+                    Mode::S_IRUSR,
+                    v.as_raw_fd()
+                );
+                Ok(v)
+            }
+            Err(e) => Err(ShmemError::MapOpenFailed(e as u32)),
+        }
+    }
+
+    fn unlink(&self, unique_id: &str) -> Result<(), ShmemError> {
+        trace!("shm_unlink({unique_id})");
+        match shm_unlink(unique_id) {
+            Ok(_) | Err(nix::Error::ENOENT) => Ok(()),
+            Err(e) => Err(ShmemError::UnlinkFailed(e as u32)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Files on a memory backed filesystem such as tmpfs or hugetlbfs, identified by their path
+pub struct TmpfsBackend;
+
+impl ShmemBackend for TmpfsBackend {
+    fn create(&self, unique_id: &str, size: usize, mode: Mode) -> Result<OwnedFd, ShmemError> {
+        let file = OpenOptions::new()
+            .create_new(true)
+            .read(true)
+            .write(true)
+            .mode({
+                #[cfg(target_os = "macos")]
+                {
+                    u32::from(mode.bits())
+                }
+                #[cfg(not(target_os = "macos"))]
+                {
+                    mode.bits()
+                }
+            })
+            .open(unique_id)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => ShmemError::MappingIdExists,
+                _ => ShmemError::MapCreateFailed(e.raw_os_error().unwrap_or(0) as u32),
+            })?;
+        truncate_new(self, unique_id, OwnedFd::from(file), size)
+    }
+
+    fn open(&self, unique_id: &str) -> Result<OwnedFd, ShmemError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(unique_id)
+            .map_err(|e| ShmemError::MapOpenFailed(e.raw_os_error().unwrap_or(0) as u32))?;
+        Ok(OwnedFd::from(file))
+    }
+
+    fn stat(&self, unique_id: &str) -> Result<FileStat, ShmemError> {
+        nix::sys::stat::stat(unique_id).map_err(|e| ShmemError::MapOpenFailed(e as u32))
+    }
+
+    fn stat_opens(&self) -> bool {
+        false
+    }
+
+    fn unlink(&self, unique_id: &str) -> Result<(), ShmemError> {
+        trace!("remove_file({unique_id})");
+        match std::fs::remove_file(unique_id) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(ShmemError::UnlinkFailed(
+                e.raw_os_error().unwrap_or(0) as u32
+            )),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, Default)]
+/// Anonymous files created with `memfd_create()`
+///
+/// These objects have no name other processes could open them with, so they can only be shared by
+/// passing their file descriptor, for example with `ShmemCommandExt`. They are freed once every
+/// process closed and unmapped them.
+pub struct MemfdBackend;
+
+#[cfg(target_os = "linux")]
+impl ShmemBackend for MemfdBackend {
+    fn create(&self, unique_id: &str, size: usize, _mode: Mode) -> Result<OwnedFd, ShmemError> {
+        // The name only shows up in /proc/<pid>/fd
+        let name = std::ffi::CString::new(unique_id).map_err(|_| ShmemError::InvalidOsId)?;
+        let fd = unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC) };
+        trace!("memfd_create({unique_id}, MFD_CLOEXEC) == {fd}");
+        if fd == -1 {
+            return Err(ShmemError::MapCreateFailed(nix::Error::last() as u32));
+        }
+        truncate_new(self, unique_id, unsafe { OwnedFd::from_raw_fd(fd) }, size)
+    }
+
+    fn open(&self, _unique_id: &str) -> Result<OwnedFd, ShmemError> {
+        Err(ShmemError::MapOpenFailed(nix::Error::ENOENT as u32))
+    }
+
+    fn can_open(&self) -> bool {
+        false
+    }
+
+    fn unlink(&self, _unique_id: &str) -> Result<(), ShmemError> {
+        Ok(())
+    }
+}
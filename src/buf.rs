@@ -0,0 +1,103 @@
+//! Zero-copy conversions between mappings and the `bytes` crate
+
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use bytes::buf::UninitSlice;
+use bytes::{BufMut, Bytes};
+
+use crate::{Protection, Shmem, ShmemError, ShmemHandle};
+
+/// Keeps the mapping alive for as long as a `Bytes` points into it
+struct ShmemBytes {
+    handle: ShmemHandle,
+    range: Range<usize>,
+}
+
+impl AsRef<[u8]> for ShmemBytes {
+    fn as_ref(&self) -> &[u8] {
+        // Safety: the range was checked by to_bytes() and the handle keeps the mapping alive
+        unsafe {
+            std::slice::from_raw_parts(self.handle.as_ptr().add(self.range.start), self.range.len())
+        }
+    }
+}
+
+impl ShmemHandle {
+    /// Returns a `Bytes` pointing at `range` of the mapping, without copying
+    ///
+    /// The `Bytes` and its clones hold a reference on the mapping, which stays mapped until they and every
+    /// `ShmemHandle` are dropped.
+    /// # Safety
+    /// The bytes in `range` must not change while the `Bytes` or any of its clones are alive, whether
+    /// through this process or another one
+    pub unsafe fn to_bytes(&self, range: Range<usize>) -> Result<Bytes, ShmemError> {
+        check_access(self, &range, Protection::ReadOnly)?;
+        Ok(Bytes::from_owner(ShmemBytes {
+            handle: self.clone(),
+            range,
+        }))
+    }
+}
+
+impl Shmem {
+    /// Returns a `BufMut` that writes into `range` of the mapping, without going through a `BytesMut`
+    ///
+    /// Like every `BufMut`, the writer panics when more bytes are put than fit in the range. Other
+    /// processes must not access the range until the writes are done.
+    pub fn buf_mut(&mut self, range: Range<usize>) -> Result<ShmemBufMut<'_>, ShmemError> {
+        check_access(self, &range, Protection::ReadWrite)?;
+        Ok(ShmemBufMut {
+            // Safety: the range lies within the mapping
+            ptr: unsafe { self.as_ptr().add(range.start) },
+            len: range.len(),
+            pos: 0,
+            _shmem: PhantomData,
+        })
+    }
+}
+
+fn check_access(shmem: &Shmem, range: &Range<usize>, access: Protection) -> Result<(), ShmemError> {
+    if range.start > range.end {
+        return Err(ShmemError::InvalidRange);
+    }
+    shmem.check_range(range.start, range.len())?;
+    if shmem.protected.restricts(range.clone(), access) {
+        return Err(ShmemError::RangeProtected);
+    }
+    Ok(())
+}
+
+/// Writes into a range of a mapping, see `Shmem::buf_mut()`
+pub struct ShmemBufMut<'a> {
+    ptr: *mut u8,
+    len: usize,
+    pos: usize,
+    _shmem: PhantomData<&'a mut Shmem>,
+}
+
+impl ShmemBufMut<'_> {
+    /// Returns how many bytes were written from the start of the range
+    pub fn written(&self) -> usize {
+        self.pos
+    }
+}
+
+unsafe impl BufMut for ShmemBufMut<'_> {
+    fn remaining_mut(&self) -> usize {
+        self.len - self.pos
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        assert!(
+            cnt <= self.remaining_mut(),
+            "cannot advance past the end of the range"
+        );
+        self.pos += cnt;
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        // Safety: the range lies within the mapping, which we borrow mutably
+        unsafe { UninitSlice::from_raw_parts_mut(self.ptr.add(self.pos), self.len - self.pos) }
+    }
+}
@@ -0,0 +1,511 @@
+//! Typed messages sent between processes through a ring buffer in a mapping
+
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::log::*;
+use crate::unix::LockByte;
+use crate::{Shmem, ShmemConf, ShmemError};
+
+mod codec;
+#[cfg(feature = "bincode")]
+pub use codec::BincodeCodec;
+#[cfg(feature = "postcard")]
+pub use codec::PostcardCodec;
+pub use codec::{Codec, JsonCodec};
+
+/// Magic value identifying a mapping that holds a channel ("SHMC")
+const CHANNEL_MAGIC: u32 = 0x434D_4853;
+/// Bumped whenever the layout of `ChannelHeader` or of the messages changes
+const CHANNEL_VERSION: u32 = 1;
+
+/// Size of the ring when `ChannelConf::capacity()` is not called
+const DEFAULT_CAPACITY: usize = 64 * 1024;
+/// Every message is prefixed with its length as a little endian u32
+const LEN_PREFIX: usize = 4;
+
+/// Slot value of a half that never connected
+const SLOT_EMPTY: u32 = 0;
+/// Slot value of a half that was dropped
+const SLOT_CLOSED: u32 = u32::MAX;
+
+/// Keeps the counters moved by each side on their own cache line
+#[repr(C, align(64))]
+struct CachePadded<T>(T);
+
+#[repr(C)]
+struct ChannelHeader {
+    magic: AtomicU32,
+    version: AtomicU32,
+    /// Size of the ring that follows the header
+    capacity: AtomicU64,
+    /// pid of the process holding each half, or one of the `SLOT_*` values. The process also holds the
+    /// lock on the byte of the object dedicated to its half for as long as it is alive.
+    sender: AtomicU32,
+    receiver: AtomicU32,
+    /// Number of bytes consumed so far, only moved by the receiver
+    head: CachePadded<AtomicU64>,
+    /// Number of bytes published so far, only moved by the sender
+    tail: CachePadded<AtomicU64>,
+}
+
+/// Offset of the ring in the mapping
+const HEADER_LEN: usize = std::mem::size_of::<ChannelHeader>();
+
+#[derive(Clone, Copy)]
+enum Side {
+    Sender,
+    Receiver,
+}
+
+/// Returns a builder for a channel of `T` messages, encoded as JSON unless `ChannelConf::codec()` is used
+///
+/// One process creates the channel with `create_sender()` or `create_receiver()`, another opens the
+/// other half by name with `open_receiver()` or `open_sender()`. Each half can only be held by one
+/// process at a time. Processes notice the other half went away through a lock the OS releases when the
+/// process holding it exits, so System V segments, which cannot be locked, are not supported.
+pub fn channel<T: Serialize + DeserializeOwned>() -> ChannelConf<T> {
+    ChannelConf {
+        shmem: ShmemConf::new(),
+        capacity: DEFAULT_CAPACITY,
+        codec: JsonCodec,
+        _msg: PhantomData,
+    }
+}
+
+/// Configures a channel, see `channel()`
+pub struct ChannelConf<T, C = JsonCodec> {
+    shmem: ShmemConf,
+    capacity: usize,
+    codec: C,
+    _msg: PhantomData<fn(T) -> T>,
+}
+
+impl<T, C: Codec> ChannelConf<T, C> {
+    /// Name of the mapping that holds the channel
+    pub fn os_id<S: AsRef<str>>(mut self, os_id: S) -> Self {
+        self.shmem = self.shmem.os_id(os_id);
+        self
+    }
+    /// Link file pointing to the mapping that holds the channel
+    pub fn flink<S: AsRef<Path>>(mut self, path: S) -> Self {
+        self.shmem = self.shmem.flink(path);
+        self
+    }
+    /// Uses `conf` to create or open the mapping, for options not covered by this builder
+    ///
+    /// The size of `conf` is ignored, the mapping is sized from `capacity()`.
+    pub fn shmem_conf(mut self, conf: ShmemConf) -> Self {
+        self.shmem = conf;
+        self
+    }
+    /// Size in bytes of the ring holding the messages that were sent but not received yet
+    ///
+    /// Every message takes 4 bytes in addition to its encoded size. Senders wait for the receiver when
+    /// the ring is full. Only used when creating the channel, which fails with `InvalidRange` if the
+    /// mapping would not fit in memory.
+    pub fn capacity(mut self, bytes: usize) -> Self {
+        self.capacity = bytes;
+        self
+    }
+    /// Encodes messages with `codec` instead of JSON. Both halves must use the same codec.
+    pub fn codec<C2: Codec>(self, codec: C2) -> ChannelConf<T, C2> {
+        ChannelConf {
+            shmem: self.shmem,
+            capacity: self.capacity,
+            codec,
+            _msg: PhantomData,
+        }
+    }
+
+    /// Creates the channel and returns its sending half
+    pub fn create_sender(self) -> Result<ShmemSender<T, C>, ShmemError> {
+        let (end, codec) = self.create(Side::Sender)?;
+        Ok(ShmemSender {
+            end,
+            codec,
+            _msg: PhantomData,
+        })
+    }
+    /// Creates the channel and returns its receiving half
+    pub fn create_receiver(self) -> Result<ShmemReceiver<T, C>, ShmemError> {
+        let (end, codec) = self.create(Side::Receiver)?;
+        Ok(ShmemReceiver {
+            end,
+            codec,
+            _msg: PhantomData,
+        })
+    }
+    /// Opens the sending half of an existing channel
+    ///
+    /// Fails with `ChannelInUse` if another live process holds it, and with `InvalidHeader` if the
+    /// channel is not initialized yet.
+    pub fn open_sender(self) -> Result<ShmemSender<T, C>, ShmemError> {
+        let (end, codec) = self.open(Side::Sender)?;
+        Ok(ShmemSender {
+            end,
+            codec,
+            _msg: PhantomData,
+        })
+    }
+    /// Opens the receiving half of an existing channel
+    ///
+    /// Fails with `ChannelInUse` if another live process holds it, and with `InvalidHeader` if the
+    /// channel is not initialized yet.
+    pub fn open_receiver(self) -> Result<ShmemReceiver<T, C>, ShmemError> {
+        let (end, codec) = self.open(Side::Receiver)?;
+        Ok(ShmemReceiver {
+            end,
+            codec,
+            _msg: PhantomData,
+        })
+    }
+
+    fn create(self, side: Side) -> Result<(Endpoint, C), ShmemError> {
+        if self.capacity <= LEN_PREFIX {
+            return Err(ShmemError::MapSizeZero);
+        }
+        let size = HEADER_LEN
+            .checked_add(self.capacity)
+            .ok_or(ShmemError::InvalidRange)?;
+        let mut shmem = self.shmem.size(size).create()?;
+        check_alignment(&shmem)?;
+        shmem.mapping.open_liveness()?;
+
+        let end = Endpoint {
+            shmem,
+            side,
+            capacity: self.capacity as u64,
+        };
+        let h = end.header();
+        h.capacity.store(self.capacity as u64, Ordering::SeqCst);
+        h.version.store(CHANNEL_VERSION, Ordering::SeqCst);
+        end.claim()?;
+        // Publish the magic last so openers never see a half initialized header
+        h.magic.store(CHANNEL_MAGIC, Ordering::Release);
+
+        debug!("Created channel '{}'", end.shmem.get_os_id());
+        Ok((end, self.codec))
+    }
+
+    fn open(self, side: Side) -> Result<(Endpoint, C), ShmemError> {
+        let mut shmem = self.shmem.open()?;
+        if shmem.len() < HEADER_LEN {
+            return Err(ShmemError::InvalidHeader);
+        }
+        check_alignment(&shmem)?;
+
+        // Safety: the mapping holds at least a header and the reference does not outlive it
+        let h = unsafe { &*(shmem.as_ptr() as *const ChannelHeader) };
+        if h.magic.load(Ordering::Acquire) != CHANNEL_MAGIC
+            || h.version.load(Ordering::SeqCst) != CHANNEL_VERSION
+        {
+            return Err(ShmemError::InvalidHeader);
+        }
+        let capacity = h.capacity.load(Ordering::SeqCst);
+        match usize::try_from(capacity) {
+            Ok(v) if v > LEN_PREFIX && v <= shmem.len() - HEADER_LEN => {}
+            _ => return Err(ShmemError::InvalidHeader),
+        }
+        shmem.mapping.open_liveness()?;
+
+        let end = Endpoint {
+            shmem,
+            side,
+            capacity,
+        };
+        end.claim()?;
+        debug!("Opened channel '{}'", end.shmem.get_os_id());
+        Ok((end, self.codec))
+    }
+}
+
+/// The header is accessed through atomics, which must be aligned
+fn check_alignment(shmem: &Shmem) -> Result<(), ShmemError> {
+    if shmem.as_ptr() as usize % std::mem::align_of::<ChannelHeader>() != 0 {
+        return Err(ShmemError::UnalignedOffset);
+    }
+    Ok(())
+}
+
+/// How long an operation waits for the other half
+#[derive(Clone, Copy)]
+enum Block {
+    No,
+    Forever,
+    Until(Instant),
+}
+
+impl Block {
+    /// Waits a little before trying again, or fails with `would_block` if we must not wait
+    fn wait(self, backoff: &mut u32, would_block: ShmemError) -> Result<(), ShmemError> {
+        match self {
+            Block::No => return Err(would_block),
+            Block::Until(deadline) if Instant::now() >= deadline => {
+                return Err(ShmemError::Timeout)
+            }
+            _ => {}
+        }
+        // Yield at first, then sleep longer and longer up to about a millisecond
+        if *backoff < 16 {
+            std::thread::yield_now();
+        } else {
+            std::thread::sleep(Duration::from_micros(1 << (*backoff - 16).min(10)));
+        }
+        *backoff += 1;
+        Ok(())
+    }
+}
+
+/// One half of a channel
+struct Endpoint {
+    shmem: Shmem,
+    side: Side,
+    capacity: u64,
+}
+
+impl Endpoint {
+    fn header(&self) -> &ChannelHeader {
+        // Safety: create()/open() made sure the mapping holds an aligned header
+        unsafe { &*(self.shmem.as_ptr() as *const ChannelHeader) }
+    }
+
+    /// Returns the slots of our half and of the other one
+    fn slots(&self) -> (&AtomicU32, &AtomicU32) {
+        let h = self.header();
+        match self.side {
+            Side::Sender => (&h.sender, &h.receiver),
+            Side::Receiver => (&h.receiver, &h.sender),
+        }
+    }
+
+    /// Returns the bytes locked by the process holding our half and the other one
+    fn lock_bytes(&self) -> (LockByte, LockByte) {
+        match self.side {
+            Side::Sender => (LockByte::Sender, LockByte::Receiver),
+            Side::Receiver => (LockByte::Receiver, LockByte::Sender),
+        }
+    }
+
+    /// Takes our half of the channel, unless a live process holds it
+    fn claim(&self) -> Result<(), ShmemError> {
+        let (byte, _) = self.lock_bytes();
+        if !self.shmem.mapping.try_lock_byte(byte)? {
+            return Err(ShmemError::ChannelInUse);
+        }
+
+        let (slot, _) = self.slots();
+        let pid = std::process::id();
+        let mut cur = slot.load(Ordering::Acquire);
+        loop {
+            // Locks held by our own process are invisible outside of Linux
+            if cur == pid {
+                return Err(ShmemError::ChannelInUse);
+            }
+            match slot.compare_exchange(cur, pid, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Ok(()),
+                Err(v) => cur = v,
+            }
+        }
+    }
+
+    /// Whether the other half was dropped or its process exited
+    ///
+    /// A half that never connected is not considered disconnected.
+    fn peer_disconnected(&self) -> bool {
+        let (_, peer) = self.slots();
+        let (_, byte) = self.lock_bytes();
+        match peer.load(Ordering::Acquire) {
+            SLOT_EMPTY => false,
+            SLOT_CLOSED => true,
+            pid if pid == std::process::id() => false,
+            // A half inherited by a forked child cannot check the lock, assume the peer is alive
+            _ => !self.shmem.mapping.is_byte_locked(byte).unwrap_or(true),
+        }
+    }
+
+    /// Copies `src` into the ring at the position `pos`, wrapping around its end
+    fn write_ring(&mut self, pos: u64, src: &[u8]) -> Result<(), ShmemError> {
+        let start = (pos % self.capacity) as usize;
+        let first = src.len().min(self.capacity as usize - start);
+        self.shmem.copy_from(HEADER_LEN + start, &src[..first])?;
+        self.shmem.copy_from(HEADER_LEN, &src[first..])
+    }
+
+    /// Fills `dst` from the ring at the position `pos`, wrapping around its end
+    fn read_ring(&self, pos: u64, dst: &mut [u8]) -> Result<(), ShmemError> {
+        let start = (pos % self.capacity) as usize;
+        let first = dst.len().min(self.capacity as usize - start);
+        let (a, b) = dst.split_at_mut(first);
+        self.shmem.copy_to(HEADER_LEN + start, a)?;
+        self.shmem.copy_to(HEADER_LEN, b)
+    }
+}
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        // Let the other half know, unless someone else took our half over in the meantime
+        let (slot, _) = self.slots();
+        let _ = slot.compare_exchange(
+            std::process::id(),
+            SLOT_CLOSED,
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// Sending half of a channel, see `channel()`
+pub struct ShmemSender<T, C = JsonCodec> {
+    end: Endpoint,
+    codec: C,
+    _msg: PhantomData<fn(T) -> T>,
+}
+
+impl<T: Serialize, C: Codec> ShmemSender<T, C> {
+    /// Sends `value`, waiting for the receiver to make room in the ring if it is full
+    ///
+    /// Fails with `Disconnected` once the receiver is gone, and with `MessageTooLarge` if the encoded
+    /// message can never fit in the ring.
+    pub fn send(&mut self, value: &T) -> Result<(), ShmemError> {
+        self.send_inner(value, Block::Forever)
+    }
+    /// Sends `value` if there is room for it in the ring, fails with `ChannelFull` otherwise
+    pub fn try_send(&mut self, value: &T) -> Result<(), ShmemError> {
+        self.send_inner(value, Block::No)
+    }
+    /// Sends `value`, waiting up to `timeout` for room in the ring
+    ///
+    /// A `timeout` too large to compute a deadline waits forever, like `send()`.
+    pub fn send_timeout(&mut self, value: &T, timeout: Duration) -> Result<(), ShmemError> {
+        let block = Instant::now()
+            .checked_add(timeout)
+            .map_or(Block::Forever, Block::Until);
+        self.send_inner(value, block)
+    }
+
+    fn send_inner(&mut self, value: &T, block: Block) -> Result<(), ShmemError> {
+        let bytes = self.codec.encode(value)?;
+        let len = match u32::try_from(bytes.len()) {
+            Ok(v) if (LEN_PREFIX + bytes.len()) as u64 <= self.end.capacity => v,
+            _ => return Err(ShmemError::MessageTooLarge(bytes.len())),
+        };
+        let needed = (LEN_PREFIX + bytes.len()) as u64;
+
+        let h = self.end.header();
+        let tail = h.tail.0.load(Ordering::Relaxed);
+        let mut backoff = 0;
+        loop {
+            if self.end.peer_disconnected() {
+                return Err(ShmemError::Disconnected);
+            }
+            let used = tail.wrapping_sub(h.head.0.load(Ordering::Acquire));
+            match self.end.capacity.checked_sub(used) {
+                Some(free) if free >= needed => break,
+                Some(_) => block.wait(&mut backoff, ShmemError::ChannelFull)?,
+                None => return Err(ShmemError::InvalidHeader),
+            }
+        }
+
+        self.end.write_ring(tail, &len.to_le_bytes())?;
+        self.end.write_ring(tail + LEN_PREFIX as u64, &bytes)?;
+        self.end
+            .header()
+            .tail
+            .0
+            .store(tail + needed, Ordering::Release);
+        Ok(())
+    }
+}
+
+impl<T, C> ShmemSender<T, C> {
+    /// Whether the receiving half was dropped or its process exited
+    pub fn is_disconnected(&self) -> bool {
+        self.end.peer_disconnected()
+    }
+    /// Returns the OS identifier of the mapping holding the channel
+    pub fn get_os_id(&self) -> &str {
+        self.end.shmem.get_os_id()
+    }
+}
+
+/// Receiving half of a channel, see `channel()`
+pub struct ShmemReceiver<T, C = JsonCodec> {
+    end: Endpoint,
+    codec: C,
+    _msg: PhantomData<fn(T) -> T>,
+}
+
+impl<T: DeserializeOwned, C: Codec> ShmemReceiver<T, C> {
+    /// Receives the next message, waiting for one to be sent if the ring is empty
+    ///
+    /// Messages sent before the sender went away are still received, `Disconnected` is returned once
+    /// they were all consumed.
+    pub fn recv(&mut self) -> Result<T, ShmemError> {
+        self.recv_inner(Block::Forever)
+    }
+    /// Receives the next message if there is one, fails with `ChannelEmpty` otherwise
+    pub fn try_recv(&mut self) -> Result<T, ShmemError> {
+        self.recv_inner(Block::No)
+    }
+    /// Receives the next message, waiting up to `timeout` for one to be sent
+    ///
+    /// A `timeout` too large to compute a deadline waits forever, like `recv()`.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, ShmemError> {
+        let block = Instant::now()
+            .checked_add(timeout)
+            .map_or(Block::Forever, Block::Until);
+        self.recv_inner(block)
+    }
+
+    fn recv_inner(&mut self, block: Block) -> Result<T, ShmemError> {
+        let h = self.end.header();
+        let head = h.head.0.load(Ordering::Relaxed);
+        let mut backoff = 0;
+        let available = loop {
+            let available = h.tail.0.load(Ordering::Acquire).wrapping_sub(head);
+            if available != 0 {
+                break available;
+            }
+            if self.end.peer_disconnected() {
+                // The sender may have published a last message right before leaving
+                if h.tail.0.load(Ordering::Acquire) == head {
+                    return Err(ShmemError::Disconnected);
+                }
+                continue;
+            }
+            block.wait(&mut backoff, ShmemError::ChannelEmpty)?;
+        };
+
+        let mut prefix = [0u8; LEN_PREFIX];
+        self.end.read_ring(head, &mut prefix)?;
+        let len = u32::from_le_bytes(prefix) as u64;
+        if available > self.end.capacity || available < LEN_PREFIX as u64 + len {
+            return Err(ShmemError::InvalidHeader);
+        }
+        let mut bytes = vec![0u8; len as usize];
+        self.end.read_ring(head + LEN_PREFIX as u64, &mut bytes)?;
+        // Consume the message even if it cannot be decoded so the next ones can be received
+        h.head
+            .0
+            .store(head + LEN_PREFIX as u64 + len, Ordering::Release);
+        self.codec.decode(&bytes)
+    }
+}
+
+impl<T, C> ShmemReceiver<T, C> {
+    /// Whether the sending half was dropped or its process exited
+    pub fn is_disconnected(&self) -> bool {
+        self.end.peer_disconnected()
+    }
+    /// Returns the OS identifier of the mapping holding the channel
+    pub fn get_os_id(&self) -> &str {
+        self.end.shmem.get_os_id()
+    }
+}
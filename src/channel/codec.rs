@@ -0,0 +1,57 @@
+//! Serialization formats of the messages sent through a channel
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::ShmemError;
+
+/// Turns messages into bytes and back
+///
+/// Both halves of a channel must use the same codec.
+pub trait Codec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, ShmemError>;
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, ShmemError>;
+}
+
+/// Encodes messages as JSON, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, ShmemError> {
+        serde_json::to_vec(value).map_err(|e| ShmemError::CodecFailed(e.into()))
+    }
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, ShmemError> {
+        serde_json::from_slice(bytes).map_err(|e| ShmemError::CodecFailed(e.into()))
+    }
+}
+
+/// Encodes messages with `bincode`
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl Codec for BincodeCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, ShmemError> {
+        bincode::serialize(value).map_err(|e| ShmemError::CodecFailed(e.into()))
+    }
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, ShmemError> {
+        bincode::deserialize(bytes).map_err(|e| ShmemError::CodecFailed(e.into()))
+    }
+}
+
+/// Encodes messages with `postcard`
+#[cfg(feature = "postcard")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PostcardCodec;
+
+#[cfg(feature = "postcard")]
+impl Codec for PostcardCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, ShmemError> {
+        postcard::to_allocvec(value).map_err(|e| ShmemError::CodecFailed(e.into()))
+    }
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, ShmemError> {
+        postcard::from_bytes(bytes).map_err(|e| ShmemError::CodecFailed(e.into()))
+    }
+}
@@ -0,0 +1,150 @@
+//! `std::io` traits over the contents of a mapping
+
+use std::convert::TryFrom;
+use std::io::{BufRead, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+
+use crate::{Shmem, ShmemError};
+
+/// Size of the buffer used by `BufRead`
+const BUF_SIZE: usize = 8 * 1024;
+
+/// Reads and writes the bytes of a mapping like a file, see `Shmem::cursor()`
+pub struct ShmemCursor<'a> {
+    shmem: &'a mut Shmem,
+    pos: u64,
+    /// Copy of the bytes starting at `buf_start`, handed out by `fill_buf()`
+    buf: Vec<u8>,
+    buf_start: u64,
+}
+
+impl Shmem {
+    /// Returns a cursor at the start of the mapping that implements `Read`, `Write`, `Seek` and `BufRead`
+    ///
+    /// Writes past `len()` fail with `ErrorKind::WriteZero` and reads past it return no bytes. Ranges
+    /// protected with `protect()` fail with `ErrorKind::PermissionDenied`. Bytes are copied in and out of
+    /// the mapping, so the cursor never hands out references to the shared memory itself. The cursor
+    /// borrows the mapping mutably, as the plain writes it performs must not race with other accesses
+    /// from this process.
+    pub fn cursor(&mut self) -> ShmemCursor<'_> {
+        ShmemCursor {
+            shmem: self,
+            pos: 0,
+            buf: Vec::new(),
+            buf_start: 0,
+        }
+    }
+}
+
+impl ShmemCursor<'_> {
+    /// Returns the current position of the cursor
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+    /// Moves the cursor to `pos`, which may lie past the end of the mapping
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+        self.buf.clear();
+    }
+    /// Returns the offset of the cursor and how many bytes are left until the end of the mapping
+    fn remaining(&self) -> (usize, usize) {
+        match usize::try_from(self.pos) {
+            Ok(offset) if offset < self.shmem.len() => (offset, self.shmem.len() - offset),
+            _ => (self.shmem.len(), 0),
+        }
+    }
+}
+
+fn io_error(e: ShmemError) -> Error {
+    match e {
+        ShmemError::RangeProtected => Error::new(ErrorKind::PermissionDenied, e),
+        e => Error::other(e),
+    }
+}
+
+impl Read for ShmemCursor<'_> {
+    fn read(&mut self, dst: &mut [u8]) -> std::io::Result<usize> {
+        // Serve what is left of the buffer first so Read and BufRead can be mixed
+        if !self.buf.is_empty() {
+            let n = self.fill_buf()?.read(dst)?;
+            self.consume(n);
+            return Ok(n);
+        }
+        let (offset, remaining) = self.remaining();
+        let n = dst.len().min(remaining);
+        self.shmem
+            .copy_to(offset, &mut dst[..n])
+            .map_err(io_error)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl BufRead for ShmemCursor<'_> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        let buf_end = self.buf_start + self.buf.len() as u64;
+        if self.pos < self.buf_start || self.pos >= buf_end {
+            let (offset, remaining) = self.remaining();
+            self.buf.resize(BUF_SIZE.min(remaining), 0);
+            self.buf_start = self.pos;
+            if let Err(e) = self.shmem.copy_to(offset, &mut self.buf) {
+                self.buf.clear();
+                return Err(io_error(e));
+            }
+        }
+        // An empty buffer means we are at the end of the mapping
+        let start = (self.pos - self.buf_start) as usize;
+        Ok(&self.buf[start..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+        if self.pos >= self.buf_start + self.buf.len() as u64 {
+            self.buf.clear();
+        }
+    }
+}
+
+impl Write for ShmemCursor<'_> {
+    fn write(&mut self, src: &[u8]) -> std::io::Result<usize> {
+        if src.is_empty() {
+            return Ok(0);
+        }
+        let (offset, remaining) = self.remaining();
+        if remaining == 0 {
+            return Err(Error::new(ErrorKind::WriteZero, ShmemError::InvalidRange));
+        }
+        let n = src.len().min(remaining);
+        self.shmem.copy_from(offset, &src[..n]).map_err(io_error)?;
+        // Buffered bytes may be outdated now
+        self.buf.clear();
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ShmemCursor<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let (base, delta) = match pos {
+            SeekFrom::Start(v) => {
+                self.set_position(v);
+                return Ok(v);
+            }
+            SeekFrom::End(v) => (self.shmem.len() as u64, v),
+            SeekFrom::Current(v) => (self.pos, v),
+        };
+        match base.checked_add_signed(delta) {
+            Some(v) => {
+                self.set_position(v);
+                Ok(v)
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
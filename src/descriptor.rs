@@ -0,0 +1,139 @@
+//! Describing mappings so they can be stored or sent to other processes (`serde` feature)
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Shmem, ShmemConf, ShmemError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How a described mapping is opened
+pub enum DescriptorBackend {
+    /// Named shared memory (`shm_open()` on unix, a file mapping on Windows)
+    Shm,
+    /// A file in `ShmemDescriptor::tmpfs_dir`
+    Tmpfs,
+    /// The System V segment identified by `key`
+    SysV { key: i64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Serializable description of a mapping, which can be turned into a `ShmemConf` to `open()` it
+///
+/// Unlike a `ShmemConf`, a descriptor never owns the mapping and dropping it has no side effects.
+pub struct ShmemDescriptor {
+    /// The os_id of the mapping, a file name in tmpfs mode
+    pub os_id: String,
+    /// Size of the mapping in bytes, as returned by `Shmem::len()`
+    pub size: usize,
+    /// How the mapping is opened
+    pub backend: DescriptorBackend,
+    /// Directory containing the file in tmpfs mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmpfs_dir: Option<PathBuf>,
+    /// Permissions the mapping was created with, if they were set explicitly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// Whether the mapping starts with an ownership header (see `ShmemConf::shared_ownership()`)
+    #[serde(default)]
+    pub shared_ownership: bool,
+    /// Whether the file is never deleted (see `ShmemConf::persistent_with_dir()`), only valid in tmpfs mode
+    #[serde(default)]
+    pub persistent: bool,
+}
+
+impl Shmem {
+    /// Returns a serializable description of this mapping
+    ///
+    /// Mappings from custom backends and anonymous mappings cannot be opened by other processes and
+    /// fail with `Unsupported`.
+    pub fn descriptor(&self) -> Result<ShmemDescriptor, ShmemError> {
+        let conf = &self.config;
+        if self.mapping.unique_id.is_empty() || conf.has_custom_backend() {
+            return Err(ShmemError::Unsupported);
+        }
+
+        let (backend, os_id, tmpfs_dir) = if let Some(key) = conf.ext.sysv_key() {
+            (
+                DescriptorBackend::SysV { key: key.into() },
+                self.mapping.unique_id.clone(),
+                None,
+            )
+        } else if conf.use_tmpfs {
+            let path = PathBuf::from(&self.mapping.unique_id);
+            let os_id = path
+                .file_name()
+                .and_then(|v| v.to_str())
+                .ok_or(ShmemError::InvalidOsId)?;
+            (
+                DescriptorBackend::Tmpfs,
+                String::from(os_id),
+                path.parent().map(PathBuf::from),
+            )
+        } else {
+            (DescriptorBackend::Shm, self.mapping.unique_id.clone(), None)
+        };
+
+        // mode_t is not a u32 on every platform
+        #[cfg(not(target_os = "windows"))]
+        #[allow(clippy::useless_conversion)]
+        let mode = conf.mode.map(|v| v.bits().into());
+        #[cfg(target_os = "windows")]
+        let mode = None;
+
+        Ok(ShmemDescriptor {
+            os_id,
+            size: self.len(),
+            backend,
+            tmpfs_dir,
+            mode,
+            shared_ownership: conf.shared_ownership,
+            persistent: conf.persistent,
+        })
+    }
+}
+
+impl std::convert::TryFrom<ShmemDescriptor> for ShmemConf {
+    type Error = ShmemError;
+
+    /// Builds the configuration that opens the described mapping
+    fn try_from(desc: ShmemDescriptor) -> Result<Self, Self::Error> {
+        let mut conf = ShmemConf::new()
+            .os_id(&desc.os_id)
+            .size(desc.size)
+            .shared_ownership(desc.shared_ownership);
+
+        #[cfg(not(target_os = "windows"))]
+        if let Some(mode) = desc.mode {
+            let mode =
+                std::convert::TryInto::try_into(mode).map_err(|_| ShmemError::Unsupported)?;
+            conf = conf.mode(crate::Mode::from_bits_truncate(mode));
+        }
+
+        if desc.persistent && desc.backend != DescriptorBackend::Tmpfs {
+            return Err(ShmemError::Unsupported);
+        }
+        match desc.backend {
+            DescriptorBackend::Shm => {}
+            #[cfg(not(target_os = "windows"))]
+            DescriptorBackend::Tmpfs => {
+                let dir = desc.tmpfs_dir.ok_or(ShmemError::NoTmpfsBaseDir)?;
+                conf = if desc.persistent {
+                    conf.persistent_with_dir(dir)
+                } else {
+                    conf.use_tmpfs_with_dir(dir)
+                };
+            }
+            #[cfg(not(target_os = "windows"))]
+            DescriptorBackend::SysV { key } => {
+                let key =
+                    std::convert::TryInto::try_into(key).map_err(|_| ShmemError::Unsupported)?;
+                conf = conf.sysv_key(key);
+            }
+            #[cfg(target_os = "windows")]
+            _ => return Err(ShmemError::Unsupported),
+        }
+        Ok(conf)
+    }
+}
@@ -1,6 +1,7 @@
 #[derive(Debug)]
 pub enum ShmemError {
     MapSizeZero,
+    MapSizeOverflow,
     NoLinkOrOsId,
     FlinkInvalidOsId,
     LinkCreateFailed(std::io::Error),
@@ -15,12 +16,47 @@ pub enum ShmemError {
     UnknownOsError(u32),
     NotInTmpfsMode,
     NoTmpfsBaseDir,
+    InvalidHeader,
+    NoSharedOwnership,
+    NotOwner,
+    OwnershipClaimFailed,
+    SnapshotIoFailed(std::io::Error),
+    SnapshotInvalid,
+    SnapshotLayoutMismatch,
+    SnapshotChecksumMismatch,
+    MapSizeMismatch(usize),
+    InvalidRange,
+    FlushFailed(u32),
+    NotPrivate,
+    UnalignedRange,
+    ProtectFailed(u32),
+    AdviseFailed(u32),
+    Unsupported,
+    LockFailed(u32),
+    MemlockLimitExceeded(u64),
+    NoNamespace,
+    InvalidNamespace,
+    KeyWithOsId,
+    ListFailed(std::io::Error),
+    UnlinkFailed(u32),
+    InvalidOsId,
+    NotInherited,
+    Timeout,
+    UnalignedOffset,
+    RangeProtected,
+    ChannelInUse,
+    ChannelFull,
+    ChannelEmpty,
+    Disconnected,
+    MessageTooLarge(usize),
+    CodecFailed(Box<dyn std::error::Error + Send + Sync>),
 }
 
 impl std::fmt::Display for ShmemError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ShmemError::MapSizeZero => f.write_str("You cannot create a shared memory mapping of 0 size"),
+            ShmemError::MapSizeOverflow => f.write_str("The size of the mapping and its guard pages does not fit in the address space"),
             ShmemError::NoLinkOrOsId => f.write_str("Tried to open mapping without flink path or os_id"),
             ShmemError::FlinkInvalidOsId => f.write_str("Tried to open mapping from both flink and os_id but the flink did not point to the same os_id"),
             ShmemError::LinkCreateFailed(err) => write!(f, "Creating the link file failed, {err}"),
@@ -35,6 +71,40 @@ impl std::fmt::Display for ShmemError {
             ShmemError::UnknownOsError(err) => write!(f, "An unexpected OS error occurred, os error {err}"),
             ShmemError::NotInTmpfsMode => f.write_str("Operation requires tmpfs mode to be enabled"),
             ShmemError::NoTmpfsBaseDir => f.write_str("No tmpfs base directory specified"),
+            ShmemError::InvalidHeader => f.write_str("The shared memory does not start with a valid header"),
+            ShmemError::NoSharedOwnership => f.write_str("Operation requires shared ownership to be enabled"),
+            ShmemError::NotOwner => f.write_str("Operation requires ownership of the shared memory"),
+            ShmemError::OwnershipClaimFailed => f.write_str("Ownership of the shared memory was not released to this process"),
+            ShmemError::SnapshotIoFailed(err) => write!(f, "Accessing the snapshot file failed, {err}"),
+            ShmemError::SnapshotInvalid => f.write_str("The file is not a valid shared memory snapshot"),
+            ShmemError::SnapshotLayoutMismatch => f.write_str("The snapshot was taken with a different layout id"),
+            ShmemError::SnapshotChecksumMismatch => f.write_str("The snapshot data does not match its checksum"),
+            ShmemError::MapSizeMismatch(size) => write!(f, "The existing shared memory has an unexpected size of {size} bytes"),
+            ShmemError::InvalidRange => f.write_str("The range does not lie within the shared memory"),
+            ShmemError::FlushFailed(err) => write!(f, "Flushing the shared memory failed, os error {err}"),
+            ShmemError::NotPrivate => f.write_str("Operation requires a private view of the shared memory"),
+            ShmemError::UnalignedRange => f.write_str("The range is not aligned to page boundaries"),
+            ShmemError::ProtectFailed(err) => write!(f, "Changing the protection of the shared memory failed, os error {err}"),
+            ShmemError::AdviseFailed(err) => write!(f, "Advising the OS about the shared memory failed, os error {err}"),
+            ShmemError::Unsupported => f.write_str("Operation is not supported on this platform"),
+            ShmemError::LockFailed(err) => write!(f, "Locking the shared memory in RAM failed, os error {err}"),
+            ShmemError::MemlockLimitExceeded(limit) => write!(f, "Locking the shared memory in RAM would exceed RLIMIT_MEMLOCK ({limit} bytes)"),
+            ShmemError::NoNamespace => f.write_str("Operation requires a namespace"),
+            ShmemError::InvalidNamespace => f.write_str("The namespace is empty, too long or contains invalid characters"),
+            ShmemError::KeyWithOsId => f.write_str("Both a key and an explicit os_id were given"),
+            ShmemError::ListFailed(err) => write!(f, "Listing the shared memory in the namespace failed, {err}"),
+            ShmemError::UnlinkFailed(err) => write!(f, "Deleting the shared memory failed, os error {err}"),
+            ShmemError::InvalidOsId => f.write_str("The os_id is not a single valid name"),
+            ShmemError::NotInherited => f.write_str("No shared memory was inherited under this name"),
+            ShmemError::Timeout => f.write_str("The shared memory was not created in time"),
+            ShmemError::UnalignedOffset => f.write_str("The offset is not aligned for the type being accessed"),
+            ShmemError::RangeProtected => f.write_str("The range is protected against this kind of access"),
+            ShmemError::ChannelInUse => f.write_str("This half of the channel is held by another process"),
+            ShmemError::ChannelFull => f.write_str("The channel has no room for the message"),
+            ShmemError::ChannelEmpty => f.write_str("The channel has no message to receive"),
+            ShmemError::Disconnected => f.write_str("The other half of the channel is gone"),
+            ShmemError::MessageTooLarge(len) => write!(f, "A message of {len} bytes does not fit in the channel"),
+            ShmemError::CodecFailed(err) => write!(f, "Encoding or decoding the message failed, {err}"),
         }
     }
 }
@@ -46,6 +116,9 @@ impl std::error::Error for ShmemError {
             ShmemError::LinkWriteFailed(err) => Some(err),
             ShmemError::LinkOpenFailed(err) => Some(err),
             ShmemError::LinkReadFailed(err) => Some(err),
+            ShmemError::SnapshotIoFailed(err) => Some(err),
+            ShmemError::ListFailed(err) => Some(err),
+            ShmemError::CodecFailed(err) => Some(err.as_ref()),
             _ => None,
         }
     }
@@ -0,0 +1,52 @@
+//! Sharing a single mapping between threads
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::Shmem;
+
+/// A cheaply cloneable handle to a mapping, usable from many threads at once
+///
+/// The mapping is unmapped (and unlinked if we own it) when the last clone is dropped. Handles only give
+/// access to the `&self` methods of `Shmem`, the same aliasing rules apply to the memory they point to.
+#[derive(Clone)]
+pub struct ShmemHandle {
+    inner: Arc<Shmem>,
+}
+
+impl ShmemHandle {
+    /// Wraps a mapping in a handle
+    pub fn new(shmem: Shmem) -> Self {
+        Self {
+            inner: Arc::new(shmem),
+        }
+    }
+    /// Returns how many clones of this handle are alive
+    pub fn handle_count(this: &Self) -> usize {
+        Arc::strong_count(&this.inner)
+    }
+    /// Returns the mapping if this is the last handle to it, gives the handle back otherwise
+    pub fn try_unwrap(this: Self) -> Result<Shmem, Self> {
+        Arc::try_unwrap(this.inner).map_err(|inner| Self { inner })
+    }
+}
+
+impl Deref for ShmemHandle {
+    type Target = Shmem;
+    fn deref(&self) -> &Shmem {
+        &self.inner
+    }
+}
+
+impl From<Shmem> for ShmemHandle {
+    fn from(shmem: Shmem) -> Self {
+        Self::new(shmem)
+    }
+}
+
+impl Shmem {
+    /// Turns the mapping into a handle that can be cloned and shared between threads
+    pub fn into_handle(self) -> ShmemHandle {
+        ShmemHandle::new(self)
+    }
+}
@@ -0,0 +1,178 @@
+//! Small metadata header stored at the start of mappings that opt into shared state
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use crate::ShmemError;
+
+/// Magic value identifying a mapping that starts with a `ShmemHeader` ("SHMH")
+const HEADER_MAGIC: u32 = 0x484D_4853;
+/// Bumped whenever the layout of `ShmemHeader` changes
+const HEADER_VERSION: u32 = 1;
+
+/// Number of bytes reserved at the start of the mapping for the header
+///
+/// Kept at a cache line so the user data that follows stays nicely aligned
+pub(crate) const HEADER_LEN: usize = 64;
+
+// Ownership token states, stored in the upper 32 bits of the token
+const STATE_UNOWNED: u64 = 0;
+const STATE_OWNED: u64 = 1;
+const STATE_HANDOFF: u64 = 2;
+const STATE_UNLINKED: u64 = 3;
+
+#[repr(C)]
+pub(crate) struct ShmemHeader {
+    magic: AtomicU32,
+    version: AtomicU32,
+    /// `(state << 32) | pid` of the process that owns (or is being handed) the mapping
+    owner_token: AtomicU64,
+}
+
+const _: () = assert!(std::mem::size_of::<ShmemHeader>() <= HEADER_LEN);
+
+fn token(state: u64, pid: u32) -> u64 {
+    (state << 32) | pid as u64
+}
+
+impl ShmemHeader {
+    /// Interprets the start of a mapping as a header
+    ///
+    /// # Safety
+    /// `ptr` must point to at least `HEADER_LEN` bytes of mapped memory that outlive the returned reference
+    pub(crate) unsafe fn from_ptr<'a>(ptr: *mut u8) -> &'a ShmemHeader {
+        &*(ptr as *const ShmemHeader)
+    }
+
+    /// Initializes a freshly created header, making `pid` the owner
+    pub(crate) fn init(&self, pid: u32) {
+        self.owner_token
+            .store(token(STATE_OWNED, pid), Ordering::SeqCst);
+        self.version.store(HEADER_VERSION, Ordering::SeqCst);
+        // Publish the magic last so openers never see a half initialized header
+        self.magic.store(HEADER_MAGIC, Ordering::Release);
+    }
+
+    /// Makes sure an opened mapping contains a header we understand
+    pub(crate) fn validate(&self) -> Result<(), ShmemError> {
+        if self.magic.load(Ordering::Acquire) != HEADER_MAGIC
+            || self.version.load(Ordering::SeqCst) != HEADER_VERSION
+        {
+            return Err(ShmemError::InvalidHeader);
+        }
+        Ok(())
+    }
+
+    /// Returns whether `pid` currently holds ownership of the mapping
+    pub(crate) fn is_owned_by(&self, pid: u32) -> bool {
+        self.owner_token.load(Ordering::SeqCst) == token(STATE_OWNED, pid)
+    }
+
+    /// Hands ownership held by `from` over to `to`. The transfer completes once `to` claims it.
+    pub(crate) fn release_to(&self, from: u32, to: u32) -> Result<(), ShmemError> {
+        self.owner_token
+            .compare_exchange(
+                token(STATE_OWNED, from),
+                token(STATE_HANDOFF, to),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .map(|_| ())
+            .map_err(|_| ShmemError::NotOwner)
+    }
+
+    /// Gives up ownership held by `pid` without designating a successor
+    pub(crate) fn release(&self, pid: u32) -> bool {
+        self.owner_token
+            .compare_exchange(
+                token(STATE_OWNED, pid),
+                token(STATE_UNOWNED, 0),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+    }
+
+    /// Takes ownership if it was handed off to `pid` or if nobody owns the mapping
+    pub(crate) fn claim(&self, pid: u32) -> Result<(), ShmemError> {
+        let mut cur = self.owner_token.load(Ordering::SeqCst);
+        loop {
+            let claimable = cur == token(STATE_OWNED, pid)
+                || cur == token(STATE_HANDOFF, pid)
+                || cur >> 32 == STATE_UNOWNED;
+            if !claimable {
+                return Err(ShmemError::OwnershipClaimFailed);
+            }
+            match self.owner_token.compare_exchange(
+                cur,
+                token(STATE_OWNED, pid),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return Ok(()),
+                Err(v) => cur = v,
+            }
+        }
+    }
+
+    /// Takes ownership away from an owner that is known to be dead
+    ///
+    /// Fails if ownership is being handed off or if the mapping was already unlinked
+    pub(crate) fn take_over(&self, pid: u32) -> bool {
+        let mut cur = self.owner_token.load(Ordering::SeqCst);
+        loop {
+            let state = cur >> 32;
+            if state != STATE_OWNED && state != STATE_UNOWNED {
+                return false;
+            }
+            match self.owner_token.compare_exchange(
+                cur,
+                token(STATE_OWNED, pid),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return true,
+                Err(v) => cur = v,
+            }
+        }
+    }
+
+    /// Takes ownership for the last process using the mapping so it can clean it up
+    ///
+    /// Also takes over a handoff nobody can claim anymore, fails if the mapping was already unlinked
+    pub(crate) fn take_over_last(&self, pid: u32) -> bool {
+        let mut cur = self.owner_token.load(Ordering::SeqCst);
+        loop {
+            if cur >> 32 == STATE_UNLINKED {
+                return false;
+            }
+            match self.owner_token.compare_exchange(
+                cur,
+                token(STATE_OWNED, pid),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return true,
+                Err(v) => cur = v,
+            }
+        }
+    }
+
+    /// Forcefully makes `pid` the owner, regardless of the current state
+    pub(crate) fn force_claim(&self, pid: u32) {
+        self.owner_token
+            .store(token(STATE_OWNED, pid), Ordering::SeqCst);
+    }
+
+    /// Marks the mapping as unlinked. Only succeeds for the current owner which
+    /// guarantees a single process ever performs the unlink.
+    pub(crate) fn mark_unlinked(&self, pid: u32) -> bool {
+        self.owner_token
+            .compare_exchange(
+                token(STATE_OWNED, pid),
+                token(STATE_UNLINKED, pid),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+    }
+}
@@ -0,0 +1,142 @@
+//! Passing mappings to child processes through inherited file descriptors
+
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::process::Command;
+use std::sync::Mutex;
+
+use nix::sys::stat::fstat;
+
+use crate::log::*;
+use crate::{os_impl, Shmem, ShmemConf, ShmemError};
+
+/// Extension for `std::process::Command` to share mappings with the spawned process
+pub trait ShmemCommandExt {
+    /// Makes `shmem` available to the spawned process, which gets it with `Shmem::from_inherited(name)`
+    ///
+    /// The file descriptor of the mapping is inherited by the child (`FD_CLOEXEC` is only cleared in the
+    /// child) and the environment variable `name` describes it, including the device and inode of the file so
+    /// the child can check it was given the right descriptor. This works for mappings without a name
+    /// such as memfds or deleted objects, but not for anonymous or System V mappings. The command keeps
+    /// its own duplicate of the descriptor, so `shmem` can be dropped before the process is spawned.
+    fn inherit_shmem(&mut self, name: &str, shmem: &Shmem) -> Result<&mut Self, ShmemError>;
+}
+
+impl ShmemCommandExt for Command {
+    fn inherit_shmem(&mut self, name: &str, shmem: &Shmem) -> Result<&mut Self, ShmemError> {
+        let fd = shmem.mapping.as_raw_fd().ok_or(ShmemError::Unsupported)?;
+        // Owned by the closure below so the number still refers to the mapping when the process is spawned
+        let dup = unsafe { BorrowedFd::borrow_raw(fd) }
+            .try_clone_to_owned()
+            .map_err(|e| ShmemError::UnknownOsError(e.raw_os_error().unwrap_or(0) as u32))?;
+        let fd = dup.as_raw_fd();
+        let stat = fstat(&dup).map_err(|e| ShmemError::UnknownOsError(e as u32))?;
+        // st_dev and st_ino are not u64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        self.env(
+            name,
+            format!(
+                "{}:{}:{}:{}:{}:{}",
+                fd,
+                shmem.mapping.map_size,
+                shmem.config.shared_ownership as u8,
+                stat.st_dev as u64,
+                stat.st_ino as u64,
+                shmem.get_os_id()
+            ),
+        );
+
+        let clear_cloexec = move || {
+            let fd = dup.as_raw_fd();
+            // Only async-signal-safe calls are allowed between fork() and exec()
+            let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+            if flags == -1
+                || unsafe { libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) } == -1
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        };
+        // Safety: the closure only calls fcntl()
+        unsafe { std::os::unix::process::CommandExt::pre_exec(self, clear_cloexec) };
+        Ok(self)
+    }
+}
+
+/// Descriptors already taken by `Shmem::from_inherited()`
+static TAKEN_FDS: Mutex<Vec<RawFd>> = Mutex::new(Vec::new());
+
+/// What the parent passed in the environment variable
+struct Inherited<'a> {
+    fd: RawFd,
+    map_size: usize,
+    shared_ownership: bool,
+    dev: u64,
+    ino: u64,
+    os_id: &'a str,
+}
+
+impl<'a> Inherited<'a> {
+    fn parse(value: &'a str) -> Option<Self> {
+        let mut parts = value.splitn(6, ':');
+        Some(Inherited {
+            fd: parts.next()?.parse().ok()?,
+            map_size: parts.next()?.parse().ok()?,
+            shared_ownership: match parts.next()? {
+                "0" => false,
+                "1" => true,
+                _ => return None,
+            },
+            dev: parts.next()?.parse().ok()?,
+            ino: parts.next()?.parse().ok()?,
+            os_id: parts.next()?,
+        })
+    }
+}
+
+impl Shmem {
+    /// Maps the shared memory passed by our parent process with `ShmemCommandExt::inherit_shmem()`
+    ///
+    /// The returned `Shmem` never owns the mapping. The inherited file descriptor is closed when it is
+    /// dropped and is not passed on to our own children. The mapping can only be taken once, later calls
+    /// fail with `NotInherited`.
+    pub fn from_inherited(name: &str) -> Result<Shmem, ShmemError> {
+        let value = std::env::var(name).map_err(|_| ShmemError::NotInherited)?;
+        let inherited = Inherited::parse(&value).ok_or(ShmemError::NotInherited)?;
+        debug!("Mapping inherited shared memory {value}");
+
+        // Held until the descriptor is ours so two threads cannot both take it
+        let mut taken = TAKEN_FDS.lock().unwrap_or_else(|e| e.into_inner());
+        if taken.contains(&inherited.fd) {
+            return Err(ShmemError::NotInherited);
+        }
+
+        // Make sure the descriptor is open and refers to the file our parent described before taking
+        // ownership of it
+        let flags = unsafe { libc::fcntl(inherited.fd, libc::F_GETFD) };
+        if flags == -1 {
+            return Err(ShmemError::NotInherited);
+        }
+        let stat = fstat(unsafe { BorrowedFd::borrow_raw(inherited.fd) })
+            .map_err(|_| ShmemError::NotInherited)?;
+        // st_dev and st_ino are not u64 on every platform
+        #[allow(clippy::unnecessary_cast)]
+        if stat.st_dev as u64 != inherited.dev
+            || stat.st_ino as u64 != inherited.ino
+            || (stat.st_size as u64) < inherited.map_size as u64
+        {
+            return Err(ShmemError::NotInherited);
+        }
+        unsafe { libc::fcntl(inherited.fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) };
+        let fd = unsafe { OwnedFd::from_raw_fd(inherited.fd) };
+        // Never taken again, even once closed and its number reused
+        taken.push(inherited.fd);
+        drop(taken);
+
+        let conf = ShmemConf::new().shared_ownership(inherited.shared_ownership);
+        let mapping = os_impl::open_mapping_fd(fd, None, inherited.os_id, &conf.ext)?;
+        if mapping.map_size != inherited.map_size {
+            return Err(ShmemError::MapSizeMismatch(mapping.map_size));
+        }
+        conf.opened(mapping)
+    }
+}
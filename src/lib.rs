@@ -2,7 +2,6 @@
 //!
 //! For help on how to get started, take a look at the [examples](https://github.com/elast0ny/shared_memory-rs/tree/master/examples) !
 
-use std::borrow::Cow;
 use std::fs::{File, OpenOptions};
 use std::io::{ErrorKind, Read, Write};
 
@@ -35,6 +34,71 @@ use crate::log::*;
 mod error;
 pub use error::*;
 
+mod header;
+use header::{ShmemHeader, HEADER_LEN};
+
+mod snapshot;
+pub use snapshot::{SnapshotConf, SnapshotInfo, SnapshotLock};
+
+mod namespace;
+mod os_id;
+
+mod wait;
+
+mod access;
+pub use access::Pod;
+
+mod cursor;
+pub use cursor::ShmemCursor;
+
+mod handle;
+pub use handle::ShmemHandle;
+
+mod protect;
+use protect::ProtectedRanges;
+pub use protect::Protection;
+
+#[cfg(not(target_os = "windows"))]
+mod backend;
+#[cfg(target_os = "linux")]
+pub use backend::MemfdBackend;
+#[cfg(not(target_os = "windows"))]
+pub use backend::{PosixShmBackend, ShmemBackend, ShmemInfo, TmpfsBackend};
+
+#[cfg(feature = "bytes")]
+mod buf;
+#[cfg(feature = "bytes")]
+pub use buf::ShmemBufMut;
+
+#[cfg(all(feature = "channel", not(target_os = "windows")))]
+mod channel;
+#[cfg(all(feature = "bincode", not(target_os = "windows")))]
+pub use channel::BincodeCodec;
+#[cfg(all(feature = "postcard", not(target_os = "windows")))]
+pub use channel::PostcardCodec;
+#[cfg(all(feature = "channel", not(target_os = "windows")))]
+pub use channel::{channel, ChannelConf, Codec, JsonCodec, ShmemReceiver, ShmemSender};
+
+#[cfg(feature = "serde")]
+mod descriptor;
+#[cfg(feature = "serde")]
+pub use descriptor::{DescriptorBackend, ShmemDescriptor};
+
+#[cfg(not(target_os = "windows"))]
+mod stale;
+#[cfg(not(target_os = "windows"))]
+pub use stale::StaleWatcher;
+
+#[cfg(not(target_os = "windows"))]
+mod inherit;
+#[cfg(not(target_os = "windows"))]
+pub use inherit::ShmemCommandExt;
+
+#[cfg(not(target_os = "windows"))]
+mod advise;
+#[cfg(not(target_os = "windows"))]
+pub use advise::Advice;
+
 //Load up the proper OS implementation
 cfg_if! {
     if #[cfg(target_os="windows")] {
@@ -61,12 +125,17 @@ pub struct ShmemConf {
     mode: Option<Mode>,
     use_tmpfs: bool,
     tmpfs_base_dir: Option<PathBuf>,
+    persistent: bool,
+    shared_ownership: bool,
+    owner_election: bool,
+    namespace: Option<String>,
+    key: Option<Vec<u8>>,
 }
 
 impl Drop for ShmemConf {
     fn drop(&mut self) {
         // Delete the flink if we are the owner of the mapping
-        if self.owner {
+        if self.owner && !self.persistent {
             if let Some(flink_path) = self.flink_path.as_ref() {
                 debug!("Deleting file link {}", flink_path.to_string_lossy());
                 let _ = remove_file(flink_path);
@@ -82,7 +151,8 @@ impl ShmemConf {
     }
     /// Provide a specific os identifier for the mapping
     ///
-    /// When not specified, a randomly generated identifier will be used
+    /// When not specified, a randomly generated identifier will be used. The identifier must be a single
+    /// name, `create()` and `open()` fail with `InvalidOsId` otherwise.
     pub fn os_id<S: AsRef<str>>(mut self, os_id: S) -> Self {
         self.os_id = Some(String::from(os_id.as_ref()));
         self
@@ -126,24 +196,183 @@ impl ShmemConf {
         self
     }
 
-    /// Get the tmpfs file path for this configuration
-    fn get_tmpfs_file_path(&self) -> Result<PathBuf, ShmemError> {
+    /// Enable persistent file-backed mode with a specific base directory
+    ///
+    /// Like `use_tmpfs_with_dir()`, but the file (and flink) are never deleted so the contents survive the
+    /// owner and, on a real disk, reboots. Use `Shmem::flush()` to make sure changes reached the file.
+    /// `create()` fails with `MappingIdExists` if the file already exists, in which case `open()` can be
+    /// used instead. When a size is set, `open()` makes sure the existing file has that size.
+    #[cfg(not(target_os = "windows"))]
+    pub fn persistent_with_dir<P: AsRef<Path>>(self, base_dir: P) -> Self {
+        let mut conf = self.use_tmpfs_with_dir(base_dir);
+        conf.persistent = true;
+        conf
+    }
+
+    /// Track ownership of the mapping through a token stored inside the mapping itself
+    ///
+    /// This reserves a small header at the start of the mapping (which is not part of `as_ptr()`/`len()`)
+    /// so that ownership can be handed between processes with `Shmem::release_ownership_to()` and
+    /// `Shmem::claim_ownership()`. Every process that opens the mapping must also enable this.
+    pub fn shared_ownership(mut self, enabled: bool) -> Self {
+        self.shared_ownership = enabled;
+        self
+    }
+
+    /// Lets the processes attached to the mapping elect a new owner if the owner dies
+    ///
+    /// The owner holds a lock on the mapping that the OS releases when it exits. Attached processes can
+    /// then take over ownership with `Shmem::elect_owner()`. The mapping is only cleaned up by the last
+    /// process to drop it: an owner leaving while others are attached gives up ownership instead. This
+    /// implies `shared_ownership(true)` and must be enabled by every process.
+    ///
+    /// Children created with `fork()` do not inherit the locks, they can neither take part in the election
+    /// nor clean up the mapping they inherited. On platforms other than Linux, locks belong to the whole
+    /// process, so each process should only open the mapping once.
+    #[cfg(not(target_os = "windows"))]
+    pub fn owner_election(mut self, enabled: bool) -> Self {
+        self.owner_election = enabled;
+        self.shared_ownership |= enabled;
+        self
+    }
+
+    /// Opens an existing mapping as a private copy-on-write view
+    ///
+    /// Writes to the view are only visible to this process and never reach the shared memory.
+    /// Use `Shmem::resync()` to discard them and see the current shared contents again.
+    ///
+    /// Fails with `Unsupported` along with `shared_ownership()` or `owner_election()`, as the ownership
+    /// header would only be updated in our private copy.
+    #[cfg(unix)]
+    pub fn open_private(mut self) -> Result<Shmem, ShmemError> {
+        if self.shared_ownership || self.owner_election {
+            return Err(ShmemError::Unsupported);
+        }
+        self.ext.private = true;
+        self.open()
+    }
+
+    /// Surrounds the mapping with `count` inaccessible pages on each side
+    ///
+    /// Accessing memory just before or after the mapping then faults instead of silently corrupting
+    /// whatever is mapped next to it. This only affects the address space of the current process, so every
+    /// process that wants this protection must enable it. Note that the end of the mapping is only
+    /// protected down to the page granularity.
+    #[cfg(unix)]
+    pub fn guard_pages(mut self, count: usize) -> Self {
+        self.ext.guard_pages = count;
+        self
+    }
+
+    /// Faults in every page of the mapping when it is created or opened (`MAP_POPULATE`)
+    ///
+    /// This avoids stalls on the first access to each page at the cost of a slower `create()`/`open()`.
+    /// Only has an effect on Linux, System V segments fail with `Unsupported`.
+    #[cfg(unix)]
+    pub fn populate(mut self, enabled: bool) -> Self {
+        self.ext.populate = enabled;
+        self
+    }
+
+    /// Maps the object twice, back to back, so accesses can run past its end and wrap around to its start
+    ///
+    /// `as_ptr().add(offset)` is then valid for `len()` bytes for any `offset` below `len()`, which lets
+    /// ring buffers read and write across the wrap point in one go. The size is rounded up to a multiple of
+    /// the page size when creating, and opening fails with `MapSizeMismatch` if it is not one. This only
+    /// affects the address space of the current process. Private views, anonymous mappings, System V
+    /// segments and mappings holding a shared ownership header are not supported.
+    #[cfg(unix)]
+    pub fn mirrored(mut self, enabled: bool) -> Self {
+        self.ext.mirrored = enabled;
+        self
+    }
+
+    /// Creates an anonymous mapping (`MAP_SHARED | MAP_ANONYMOUS`) that is shared with forked children
+    ///
+    /// The mapping has no name and no filesystem entry, so `os_id()`, `flink()`, tmpfs mode and the
+    /// backend are ignored and `get_os_id()` returns an empty string. Every process that inherited the
+    /// mapping simply unmaps it when dropping its `Shmem`. Private views and owner election are not
+    /// supported.
+    #[cfg(unix)]
+    pub fn anonymous(mut self) -> Result<Shmem, ShmemError> {
+        if self.size == 0 {
+            return Err(ShmemError::MapSizeZero);
+        }
+        if self.owner_election {
+            return Err(ShmemError::Unsupported);
+        }
+        self.size += self.data_offset();
+        self.flink_path = None;
+
+        let mapping = os_impl::create_mapping_anonymous(self.size, &self.ext)?;
+        if self.shared_ownership {
+            // Safety: the mapping is at least HEADER_LEN bytes and lives as long as the header ref
+            unsafe { ShmemHeader::from_ptr(mapping.as_mut_ptr()) }.init(std::process::id());
+        }
+
+        self.owner = true;
+        Ok(Shmem {
+            config: self,
+            mapping,
+            protected: ProtectedRanges::default(),
+        })
+    }
+
+    /// Uses `backend` to create, open and delete the objects that get mapped
+    ///
+    /// Defaults to `PosixShmBackend`, or `TmpfsBackend` in tmpfs mode.
+    #[cfg(unix)]
+    pub fn backend<B: ShmemBackend + 'static>(mut self, backend: B) -> Self {
+        self.ext.backend = Some(std::sync::Arc::new(backend));
+        self
+    }
+
+    /// Uses the System V shared memory segment identified by `key` instead of a POSIX one
+    ///
+    /// This allows sharing memory with programs that use `shmget()`/`shmat()`. `os_id()`, `flink()` and
+    /// tmpfs mode are ignored and `get_os_id()` returns the key in hexadecimal, as printed by `ipcs`.
+    /// The owner removes the segment (`IPC_RMID`) when dropped; it is destroyed once every process
+    /// detached from it. Private views, guard pages, mirrored views, `populate()` and owner election are
+    /// not supported and make `create()`/`open()` fail with `Unsupported`.
+    #[cfg(unix)]
+    pub fn sysv_key(mut self, key: libc::key_t) -> Self {
+        self.ext.sysv_key = Some(key);
+        self
+    }
+
+    /// Returns the number of bytes reserved at the start of the mapping
+    fn data_offset(&self) -> usize {
+        if self.shared_ownership {
+            HEADER_LEN
+        } else {
+            0
+        }
+    }
+
+    /// Get the identifier of the object for `os_id` that is passed to the backend
+    ///
+    /// In tmpfs mode, this is the path of the file.
+    fn object_id(&self, os_id: &str) -> Result<String, ShmemError> {
         if !self.use_tmpfs {
-            return Err(ShmemError::NotInTmpfsMode);
+            return Ok(String::from(os_id));
         }
+        let path = self.get_tmpfs_dir()?.join(os_id);
+        path.to_str()
+            .map(String::from)
+            .ok_or(ShmemError::UnknownOsError(0))
+    }
 
+    /// Get the directory holding the tmpfs files, which is specific to our namespace if any
+    fn get_tmpfs_dir(&self) -> Result<PathBuf, ShmemError> {
         let base_dir = self
             .tmpfs_base_dir
             .as_ref()
             .ok_or(ShmemError::NoTmpfsBaseDir)?;
 
-        if let Some(ref os_id) = self.os_id {
-            // Use os_id as filename
-            Ok(base_dir.join(os_id))
-        } else {
-            // Generate random filename
-            Ok(base_dir.join(format!("shmem_{:X}", rand::random::<u64>())))
-        }
+        Ok(match self.namespace {
+            Some(ref namespace) => base_dir.join(namespace),
+            None => base_dir.clone(),
+        })
     }
 
     /// Create a new mapping using the current configuration
@@ -151,6 +380,14 @@ impl ShmemConf {
         if self.size == 0 {
             return Err(ShmemError::MapSizeZero);
         }
+        self.size += self.data_offset();
+        self.validate_namespace()?;
+        self.resolve_key()?;
+        self.normalize_os_id()?;
+        if self.ext.sysv_key().is_some() {
+            // System V segments are only found through their key
+            self.flink_path = None;
+        }
 
         if let Some(ref flink_path) = self.flink_path {
             if !self.overwrite_flink && flink_path.is_file() {
@@ -159,64 +396,53 @@ impl ShmemConf {
         }
 
         // Create the mapping
-        let mapping = if cfg!(not(target_os = "windows")) && self.use_tmpfs {
-            // tmpfs mode
-            if self.os_id.is_some() {
-                // Use specified os_id
-                let tmpfs_file_path = self.get_tmpfs_file_path()?;
-                os_impl::create_mapping_tmpfs(
-                    tmpfs_file_path
-                        .to_str()
-                        .ok_or(ShmemError::UnknownOsError(0))?,
-                    self.size,
-                    #[cfg(not(target_os = "windows"))]
-                    self.mode,
-                )?
-            } else {
-                // Generate random filename until one works
-                loop {
-                    let random_path = self.get_tmpfs_file_path()?;
-                    match os_impl::create_mapping_tmpfs(
-                        random_path.to_str().ok_or(ShmemError::UnknownOsError(0))?,
-                        self.size,
-                        #[cfg(not(target_os = "windows"))]
-                        self.mode,
-                    ) {
-                        Err(ShmemError::MappingIdExists) => continue,
-                        Ok(m) => break m,
-                        Err(e) => return Err(e),
-                    }
-                }
-            }
+        let mut mapping = if let Some(key) = self.ext.sysv_key() {
+            // System V mode
+            os_impl::create_mapping_sysv(
+                key,
+                self.size,
+                #[cfg(not(target_os = "windows"))]
+                self.mode,
+                &self.ext,
+                self.owner_election,
+            )?
         } else {
-            // shm_open mode
+            if self.use_tmpfs && self.namespace.is_some() {
+                let dir = self.get_tmpfs_dir()?;
+                std::fs::create_dir_all(&dir).map_err(|e| {
+                    ShmemError::MapCreateFailed(e.raw_os_error().unwrap_or(0) as u32)
+                })?;
+            }
             match self.os_id {
                 None => {
                     // Generate random ID until one works
                     loop {
-                        let cur_id = format!("/shmem_{:X}", rand::random::<u64>());
-                        match os_impl::create_mapping(
-                            &cur_id,
-                            self.size,
-                            #[cfg(not(target_os = "windows"))]
-                            self.mode,
-                        ) {
+                        let cur_id = self.object_id(&self.generated_os_id(rand::random()))?;
+                        match self.create_object(&cur_id) {
                             Err(ShmemError::MappingIdExists) => continue,
                             Ok(m) => break m,
                             Err(e) => return Err(e),
                         }
                     }
                 }
-                Some(ref specific_id) => os_impl::create_mapping(
-                    specific_id,
-                    self.size,
-                    #[cfg(not(target_os = "windows"))]
-                    self.mode,
-                )?,
+                Some(ref specific_id) => self.create_object(&self.object_id(specific_id)?)?,
             }
         };
 
         debug!("Created shared memory mapping '{}'", mapping.unique_id);
+        mapping.set_persistent(self.persistent);
+
+        if self.shared_ownership {
+            // Safety: the mapping is at least HEADER_LEN bytes and lives as long as the header ref
+            unsafe { ShmemHeader::from_ptr(mapping.as_mut_ptr()) }.init(std::process::id());
+        }
+        if self.owner_election {
+            mapping.open_liveness()?;
+            mapping.attach()?;
+            if !mapping.try_lock_liveness()? {
+                return Err(ShmemError::OwnershipClaimFailed);
+            }
+        }
 
         // Create flink
         if let Some(ref flink_path) = self.flink_path {
@@ -257,11 +483,25 @@ impl ShmemConf {
         Ok(Shmem {
             config: self,
             mapping,
+            protected: ProtectedRanges::default(),
         })
     }
 
     /// Opens an existing mapping using the current configuration
-    pub fn open(mut self) -> Result<Shmem, ShmemError> {
+    pub fn open(self) -> Result<Shmem, ShmemError> {
+        self.open_with_flink_retries(true)
+    }
+
+    /// Opens an existing mapping, sleeping and retrying a few times while the flink is being written
+    /// if `flink_retries` is set
+    pub(crate) fn open_with_flink_retries(
+        mut self,
+        flink_retries: bool,
+    ) -> Result<Shmem, ShmemError> {
+        self.validate_namespace()?;
+        self.resolve_key()?;
+        self.normalize_os_id()?;
+
         // Must at least have a flink or an os_id (except in tmpfs mode where we might infer the path)
         if self.flink_path.is_none()
             && self.os_id.is_none()
@@ -272,20 +512,20 @@ impl ShmemConf {
             return Err(ShmemError::NoLinkOrOsId);
         }
 
+        if let Some(key) = self.ext.sysv_key() {
+            // System V mode: the key is all we need
+            self.flink_path = None;
+            let mapping = os_impl::open_mapping_sysv(key, &self.ext, self.owner_election)?;
+            return self.opened(mapping);
+        }
+
         let mut flink_content = String::new();
-        let mut retry = 0;
+        let mut retry = if flink_retries { 0 } else { 5 };
 
         loop {
-            let target_identifier: Cow<str> = if let Some(ref unique_id) = self.os_id {
+            let target_identifier = if let Some(ref unique_id) = self.os_id {
                 retry = 5;
-                if cfg!(not(target_os = "windows")) && self.use_tmpfs {
-                    // tmpfs mode: convert os_id to file path
-                    let tmpfs_path = self.get_tmpfs_file_path()?;
-                    Cow::Owned(tmpfs_path.to_string_lossy().into_owned())
-                } else {
-                    // shm_open mode: use os_id directly
-                    unique_id.as_str().into()
-                }
+                self.object_id(unique_id)?
             } else if let Some(ref flink_path) = self.flink_path {
                 // Read from flink file
                 debug!(
@@ -296,31 +536,24 @@ impl ShmemConf {
                 flink_content.clear();
                 f.read_to_string(&mut flink_content)
                     .map_err(ShmemError::LinkReadFailed)?;
-                flink_content.as_str().into()
+                match self.flink_target(&flink_content) {
+                    Ok(v) => v,
+                    // The owner might not have written the full identifier to the file yet
+                    Err(ShmemError::InvalidOsId) if retry < 5 => {
+                        retry += 1;
+                        std::thread::sleep(std::time::Duration::from_millis(50));
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                }
             } else {
                 return Err(ShmemError::NoLinkOrOsId);
             };
 
-            let mapping_result = {
-                if cfg!(not(target_os = "windows")) && self.use_tmpfs {
-                    // tmpfs mode: target_identifier is a file path
-                    os_impl::open_mapping_tmpfs(&target_identifier, self.size)
-                } else {
-                    // shm_open mode: target_identifier is shm ID
-                    os_impl::open_mapping(&target_identifier, self.size, &self.ext)
-                }
-            };
+            let mapping_result = self.open_object(&target_identifier);
 
             match mapping_result {
-                Ok(m) => {
-                    self.size = m.map_size;
-                    self.owner = false;
-
-                    return Ok(Shmem {
-                        config: self,
-                        mapping: m,
-                    });
-                }
+                Ok(m) => return self.opened(m),
                 // If we got this failing from the flink, try again in case the owner didn't write the full
                 // identifier to the file yet
                 Err(ShmemError::MapOpenFailed(_)) if self.os_id.is_none() && retry < 5 => {
@@ -331,29 +564,222 @@ impl ShmemConf {
             }
         }
     }
+
+    /// Checks a mapping we just opened against the current configuration
+    fn opened(mut self, mut m: os_impl::MapData) -> Result<Shmem, ShmemError> {
+        if self.persistent && self.size != 0 && m.map_size != self.size + self.data_offset() {
+            return Err(ShmemError::MapSizeMismatch(
+                m.map_size.saturating_sub(self.data_offset()),
+            ));
+        }
+        m.set_persistent(self.persistent);
+        if self.shared_ownership {
+            if m.map_size < HEADER_LEN {
+                return Err(ShmemError::InvalidHeader);
+            }
+            // Safety: we just made sure the mapping can hold a header
+            unsafe { ShmemHeader::from_ptr(m.as_mut_ptr()) }.validate()?;
+        }
+        if self.owner_election {
+            m.open_liveness()?;
+            m.attach()?;
+        }
+        self.size = m.map_size;
+        self.owner = false;
+
+        Ok(Shmem {
+            config: self,
+            mapping: m,
+            protected: ProtectedRanges::default(),
+        })
+    }
 }
 
 /// Structure used to extract information from an existing shared memory mapping
+///
+/// # Aliasing
+/// The memory behind `as_ptr()` can be written at any time by other processes, and by other threads holding
+/// the same mapping. The only references `Shmem` creates to it on its own are atomics: the ownership
+/// header used by `shared_ownership()` and `owner_election()`, the values of the `atomic_*_at()` accessors
+/// and the control block of a `channel()`, which other processes must also only access atomically. The
+/// safe methods that write plain bytes (`write_at()`, `copy_from()`, `cursor()`) take `&mut self`, so
+/// within this process they cannot race with reads such as `read_at()` or `copy_to()`.
+/// Accesses through the raw pointer must be synchronized by the caller (atomics, volatile accesses or a
+/// lock stored in the mapping), and a `&[u8]` or `&mut [u8]` obtained from `as_slice()`/`as_slice_mut()`
+/// must not overlap with a concurrent write. The same goes for the `Bytes` and `BufMut` returned by
+/// `ShmemHandle::to_bytes()` and `Shmem::buf_mut()` (`bytes` feature). Moving a `Shmem` to another
+/// thread, or sharing it through a `ShmemHandle`, does not change these rules.
 pub struct Shmem {
     config: ShmemConf,
     mapping: os_impl::MapData,
+    protected: ProtectedRanges,
+}
+
+// Safety: the mapping is not tied to the thread that created it and the methods taking `&self` only
+// perform system calls or read state that never changes behind a shared reference. Through `&self`,
+// the memory is only written with atomics and otherwise only read, plain writes require `&mut self`,
+// see the aliasing rules above.
+unsafe impl Send for Shmem {}
+unsafe impl Sync for Shmem {}
+
+impl Drop for Shmem {
+    fn drop(&mut self) {
+        if self.config.owner_election {
+            self.leave_election();
+        }
+        // Make sure exactly one process performs the cleanup when ownership is shared
+        if let Some(h) = self.header() {
+            let is_owner = self.config.owner && h.mark_unlinked(std::process::id());
+            self.mapping.set_owner(is_owner);
+            self.config.owner = is_owner;
+        }
+    }
 }
 #[allow(clippy::len_without_is_empty)]
 impl Shmem {
-    /// Returns whether we created the mapping or not
+    /// Returns whether we own the mapping or not
+    ///
+    /// With `ShmemConf::shared_ownership()`, this reflects the ownership token stored in the mapping
     pub fn is_owner(&self) -> bool {
-        self.config.owner
+        match self.header() {
+            Some(h) => self.config.owner && h.is_owned_by(std::process::id()),
+            None => self.config.owner,
+        }
     }
     /// Allows for gaining/releasing ownership of the mapping
     ///
+    /// With `ShmemConf::shared_ownership()`, gaining ownership forcefully takes it away from any other process.
+    /// Prefer `release_ownership_to()` and `claim_ownership()` to move ownership safely.
+    ///
     /// Warning : You must ensure at least one process owns the mapping in order to ensure proper cleanup code is ran
     pub fn set_owner(&mut self, is_owner: bool) -> bool {
-        self.mapping.set_owner(is_owner);
+        let prev_val = self.is_owner();
+        if let Some(h) = self.header() {
+            let pid = std::process::id();
+            if is_owner {
+                h.force_claim(pid);
+                if self.config.owner_election {
+                    let _ = self.mapping.try_lock_liveness();
+                }
+            } else if prev_val && h.release(pid) && self.config.owner_election {
+                self.mapping.unlock_liveness();
+            }
+        }
 
-        let prev_val = self.config.owner;
+        self.mapping.set_owner(is_owner);
         self.config.owner = is_owner;
         prev_val
     }
+    /// Hands ownership of the mapping over to the process `pid`
+    ///
+    /// The mapping is left without an owner until `pid` calls `claim_ownership()`. Requires `ShmemConf::shared_ownership()`.
+    pub fn release_ownership_to(&mut self, pid: u32) -> Result<(), ShmemError> {
+        let h = self.header().ok_or(ShmemError::NoSharedOwnership)?;
+        if !self.config.owner {
+            return Err(ShmemError::NotOwner);
+        }
+        h.release_to(std::process::id(), pid)?;
+        if self.config.owner_election {
+            self.mapping.unlock_liveness();
+        }
+
+        debug!(
+            "Released ownership of '{}' to pid {}",
+            self.get_os_id(),
+            pid
+        );
+        self.mapping.set_owner(false);
+        self.config.owner = false;
+        Ok(())
+    }
+    /// Takes ownership of the mapping if it was handed to us with `release_ownership_to()` or if nobody owns it
+    ///
+    /// Requires `ShmemConf::shared_ownership()`.
+    pub fn claim_ownership(&mut self) -> Result<(), ShmemError> {
+        let h = self.header().ok_or(ShmemError::NoSharedOwnership)?;
+        if self.config.owner_election && !self.mapping.try_lock_liveness()? {
+            return Err(ShmemError::OwnershipClaimFailed);
+        }
+        if let Err(e) = h.claim(std::process::id()) {
+            if self.config.owner_election {
+                self.mapping.unlock_liveness();
+            }
+            return Err(e);
+        }
+
+        debug!("Claimed ownership of '{}'", self.get_os_id());
+        self.mapping.set_owner(true);
+        self.config.owner = true;
+        Ok(())
+    }
+    /// Takes ownership of the mapping if its owner has died
+    ///
+    /// Returns whether we own the mapping. When many processes race to take over a dead owner, only one of
+    /// them wins. Requires `ShmemConf::owner_election()`.
+    #[cfg(not(target_os = "windows"))]
+    pub fn elect_owner(&mut self) -> Result<bool, ShmemError> {
+        if !self.config.owner_election {
+            return Err(ShmemError::NoSharedOwnership);
+        }
+        if self.is_owner() {
+            return Ok(true);
+        }
+        self.try_take_over()
+    }
+    /// Takes over ownership if nobody holds the liveness lock
+    fn try_take_over(&mut self) -> Result<bool, ShmemError> {
+        let h = self.header().ok_or(ShmemError::NoSharedOwnership)?;
+        // The owner holds the lock for as long as it is alive
+        if !self.mapping.try_lock_liveness()? {
+            return Ok(false);
+        }
+        if !h.take_over(std::process::id()) {
+            self.mapping.unlock_liveness();
+            return Ok(false);
+        }
+
+        debug!("Took over ownership of '{}'", self.get_os_id());
+        self.mapping.set_owner(true);
+        self.config.owner = true;
+        Ok(true)
+    }
+    /// Stops taking part in owner election, the last process attached to the mapping becomes its owner
+    fn leave_election(&mut self) {
+        let h = match self.header() {
+            Some(h) => h,
+            None => return,
+        };
+        let pid = std::process::id();
+        let is_owner = match self.mapping.detach() {
+            // Wait for an owner that is leaving too, it releases its lock right away
+            Ok(true) => {
+                self.is_owner() || (self.mapping.lock_liveness().is_ok() && h.take_over_last(pid))
+            }
+            // Let whoever stays clean up, a survivor can still elect itself
+            Ok(false) => {
+                if self.is_owner() && h.release(pid) {
+                    self.mapping.unlock_liveness();
+                }
+                false
+            }
+            // We are a forked child, the mapping belongs to our parent
+            Err(_) => false,
+        };
+        if is_owner {
+            debug!("Last process attached to '{}'", self.get_os_id());
+        }
+        self.mapping.set_owner(is_owner);
+        self.config.owner = is_owner;
+    }
+    /// Returns the header stored at the start of the mapping, if any
+    fn header(&self) -> Option<&ShmemHeader> {
+        if self.config.shared_ownership {
+            // Safety: open()/create() made sure the mapping holds a valid header
+            Some(unsafe { ShmemHeader::from_ptr(self.mapping.as_mut_ptr()) })
+        } else {
+            None
+        }
+    }
     /// Returns the OS unique identifier for the mapping
     pub fn get_os_id(&self) -> &str {
         self.mapping.unique_id.as_str()
@@ -362,8 +788,9 @@ impl Shmem {
     /// Returns the tmpfs path if present
     #[cfg(not(target_os = "windows"))]
     pub fn get_tmpfs_file_path(&self) -> Option<PathBuf> {
-        if self.config.use_tmpfs {
-            self.config.get_tmpfs_file_path().ok()
+        if self.config.use_tmpfs && self.config.ext.sysv_key().is_none() {
+            // The mapping is identified by the path of its file
+            Some(PathBuf::from(&self.mapping.unique_id))
         } else {
             None
         }
@@ -375,22 +802,113 @@ impl Shmem {
     }
     /// Returns the total size of the mapping
     pub fn len(&self) -> usize {
-        self.mapping.map_size
+        self.mapping.map_size - self.config.data_offset()
     }
     /// Returns a raw pointer to the mapping
     pub fn as_ptr(&self) -> *mut u8 {
-        self.mapping.as_mut_ptr()
+        // Safety: the offset always lies within the mapping
+        unsafe { self.mapping.as_mut_ptr().add(self.config.data_offset()) }
+    }
+    /// Synchronously flushes changes made to the mapping to its backing object
+    #[cfg(not(target_os = "windows"))]
+    pub fn flush(&self) -> Result<(), ShmemError> {
+        self.flush_range(0, self.len())
+    }
+    /// Schedules changes made to the mapping to be flushed to its backing object, without waiting
+    #[cfg(not(target_os = "windows"))]
+    pub fn flush_async(&self) -> Result<(), ShmemError> {
+        self.flush_async_range(0, self.len())
+    }
+    /// Synchronously flushes changes made to `len` bytes starting at `offset`
+    #[cfg(not(target_os = "windows"))]
+    pub fn flush_range(&self, offset: usize, len: usize) -> Result<(), ShmemError> {
+        self.check_range(offset, len)?;
+        self.mapping
+            .flush(self.config.data_offset() + offset, len, true)
+    }
+    /// Schedules changes made to `len` bytes starting at `offset` to be flushed, without waiting
+    #[cfg(not(target_os = "windows"))]
+    pub fn flush_async_range(&self, offset: usize, len: usize) -> Result<(), ShmemError> {
+        self.check_range(offset, len)?;
+        self.mapping
+            .flush(self.config.data_offset() + offset, len, false)
+    }
+    /// Locks the mapping in RAM so accessing it never waits on page faults or swap
+    ///
+    /// Unprivileged processes can only lock up to `RLIMIT_MEMLOCK` bytes, `MemlockLimitExceeded` is returned
+    /// when the mapping doesn't fit in that limit.
+    #[cfg(not(target_os = "windows"))]
+    pub fn lock(&self) -> Result<(), ShmemError> {
+        self.mapping.lock()
+    }
+    /// Undoes `lock()`, allowing the mapping to be paged out again
+    #[cfg(not(target_os = "windows"))]
+    pub fn unlock(&self) -> Result<(), ShmemError> {
+        self.mapping.unlock()
+    }
+    /// Discards the changes made to a view opened with `ShmemConf::open_private()`
+    ///
+    /// The view reflects the current shared contents again afterwards, with the same protections
+    #[cfg(not(target_os = "windows"))]
+    pub fn resync(&mut self) -> Result<(), ShmemError> {
+        self.mapping.resync_private(&self.config.ext)?;
+        // Mapping the view again reset its protection
+        let offset = self.config.data_offset();
+        for (range, prot) in self.protected.iter() {
+            self.mapping
+                .protect(offset + range.start, range.end - range.start, prot)?;
+        }
+        Ok(())
+    }
+    /// Makes sure `len` bytes starting at `offset` lie within the mapping
+    fn check_range(&self, offset: usize, len: usize) -> Result<(), ShmemError> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.len() => Ok(()),
+            _ => Err(ShmemError::InvalidRange),
+        }
     }
     /// Returns mapping as a byte slice
     /// # Safety
     /// This function is unsafe because it is impossible to ensure the range of bytes is immutable
+    /// # Panics
+    /// Panics if part of the mapping was made inaccessible with `protect()`, see `try_as_slice()`
     pub unsafe fn as_slice(&self) -> &[u8] {
-        std::slice::from_raw_parts(self.as_ptr(), self.len())
+        self.try_as_slice()
+            .expect("part of the mapping is protected against reads")
     }
     /// Returns mapping as a mutable byte slice
     /// # Safety
     /// This function is unsafe because it is impossible to ensure the returned mutable refence is unique/exclusive
+    /// # Panics
+    /// Panics if part of the mapping was made read-only or inaccessible with `protect()`, see `try_as_slice_mut()`
     pub unsafe fn as_slice_mut(&mut self) -> &mut [u8] {
-        std::slice::from_raw_parts_mut(self.as_ptr(), self.len())
+        self.try_as_slice_mut()
+            .expect("part of the mapping is protected against writes")
+    }
+    /// Returns mapping as a byte slice, failing with `ShmemError::RangeProtected` if part of it was made
+    /// inaccessible with `protect()`
+    /// # Safety
+    /// This function is unsafe because it is impossible to ensure the range of bytes is immutable
+    pub unsafe fn try_as_slice(&self) -> Result<&[u8], ShmemError> {
+        if self
+            .protected
+            .restricts(0..self.len(), Protection::ReadOnly)
+        {
+            return Err(ShmemError::RangeProtected);
+        }
+        Ok(std::slice::from_raw_parts(self.as_ptr(), self.len()))
+    }
+    /// Returns mapping as a mutable byte slice, failing with `ShmemError::RangeProtected` if part of it was
+    /// made read-only or inaccessible with `protect()`
+    /// # Safety
+    /// This function is unsafe because it is impossible to ensure the returned mutable refence is unique/exclusive
+    pub unsafe fn try_as_slice_mut(&mut self) -> Result<&mut [u8], ShmemError> {
+        if self
+            .protected
+            .restricts(0..self.len(), Protection::ReadWrite)
+        {
+            return Err(ShmemError::RangeProtected);
+        }
+        Ok(std::slice::from_raw_parts_mut(self.as_ptr(), self.len()))
     }
 }
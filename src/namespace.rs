@@ -0,0 +1,148 @@
+//! Generating os_ids from keys and scoping them to a namespace
+
+use crate::os_id::{MAX_NAME_LEN, MAX_SHM_ID_LEN};
+use crate::snapshot::Fnv1a;
+use crate::{ShmemConf, ShmemError};
+
+/// Length of the `.<id>` suffix of generated os_ids
+const GENERATED_SUFFIX_LEN: usize = 1 + 16;
+
+#[cfg(not(target_os = "windows"))]
+use crate::{log::*, os_impl};
+
+impl ShmemConf {
+    /// Generates ids and tmpfs files within `namespace`
+    ///
+    /// Random os_ids look like `/<namespace>.<random>` instead of `/shmem_<random>` and tmpfs files are
+    /// created in a `<namespace>` subdirectory of the base directory. Explicit os_ids are used as-is,
+    /// except in tmpfs mode where they are also looked up in the subdirectory.
+    ///
+    /// The namespace must not be empty, `.`, `..` or contain `/`. It must also leave room for the generated
+    /// part of os_ids: `shm_open()` names are limited to 255 bytes, or 31 on macOS, which leaves 13 bytes for
+    /// the namespace.
+    pub fn namespace<S: AsRef<str>>(mut self, namespace: S) -> Self {
+        self.namespace = Some(String::from(namespace.as_ref()));
+        self
+    }
+
+    /// Makes sure the namespace, if any, can be used in os_ids and paths
+    pub(crate) fn validate_namespace(&self) -> Result<(), ShmemError> {
+        // Generated os_ids are `/<namespace>.<id>`, tmpfs mode uses the namespace as a directory name
+        let max_len = if self.use_tmpfs {
+            MAX_NAME_LEN
+        } else {
+            MAX_SHM_ID_LEN - 1 - GENERATED_SUFFIX_LEN
+        };
+        match self.namespace.as_deref() {
+            Some("") | Some(".") | Some("..") => Err(ShmemError::InvalidNamespace),
+            Some(ns) if ns.contains(['/', '\0']) || ns.len() > max_len => {
+                Err(ShmemError::InvalidNamespace)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Derives the os_id of the mapping from `key` instead of using `os_id()`
+    ///
+    /// The key is hashed along with the namespace, so unrelated processes that agree on a key and a
+    /// namespace open the same mapping without exchanging its name through a flink. Like System V
+    /// `ftok()`, different keys may collide. The key can be of any length, the os_id looks like
+    /// `/<namespace>.<hash>` (`/shmem_<hash>` without namespace) or `shmem_<hash>` in tmpfs mode.
+    ///
+    /// `create()` and `open()` fail with `ShmemError::KeyWithOsId` if an os_id is given as well.
+    pub fn key<K: AsRef<[u8]>>(mut self, key: K) -> Self {
+        self.key = Some(Vec::from(key.as_ref()));
+        self
+    }
+
+    /// Replaces the os_id with the one derived from our key, if any
+    ///
+    /// Fails if an os_id was also given, as it would silently be ignored.
+    pub(crate) fn resolve_key(&mut self) -> Result<(), ShmemError> {
+        if let Some(ref key) = self.key {
+            if self.os_id.is_some() {
+                return Err(ShmemError::KeyWithOsId);
+            }
+            let mut hash = Fnv1a::new();
+            if let Some(ref namespace) = self.namespace {
+                hash.update(namespace.as_bytes());
+            }
+            // Keeps ("ab", "c") and ("a", "bc") apart
+            hash.update(&[0]);
+            hash.update(key);
+            self.os_id = Some(self.generated_os_id(hash.0));
+        }
+        Ok(())
+    }
+
+    /// Formats a generated os_id within our namespace
+    ///
+    /// tmpfs files do not need the namespace in their name as they live in its subdirectory
+    pub(crate) fn generated_os_id(&self, id: u64) -> String {
+        if self.use_tmpfs {
+            format!("shmem_{id:016X}")
+        } else if let Some(ref namespace) = self.namespace {
+            format!("/{namespace}.{id:016X}")
+        } else {
+            format!("/shmem_{id:016X}")
+        }
+    }
+
+    /// Lists the os_ids of the mappings that currently exist in our namespace
+    ///
+    /// In tmpfs mode, these are the names of the files in the namespace subdirectory. Otherwise, these
+    /// are the shared memory objects named `/<namespace>.<suffix>`, where the suffix contains no `.`, which
+    /// can only be enumerated on Linux.
+    #[cfg(not(target_os = "windows"))]
+    pub fn list(&self) -> Result<Vec<String>, ShmemError> {
+        self.validate_namespace()?;
+        let namespace = self.namespace.as_ref().ok_or(ShmemError::NoNamespace)?;
+
+        let mut os_ids = if self.use_tmpfs {
+            let dir = match std::fs::read_dir(self.get_tmpfs_dir()?) {
+                Ok(v) => v,
+                // Nothing was ever created in this namespace
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => return Err(ShmemError::ListFailed(e)),
+            };
+            let mut os_ids = Vec::new();
+            for entry in dir {
+                let entry = entry.map_err(ShmemError::ListFailed)?;
+                if let Some(name) = entry.file_name().to_str() {
+                    os_ids.push(String::from(name));
+                }
+            }
+            os_ids
+        } else {
+            let prefix = format!("{namespace}.");
+            let mut os_ids = os_impl::list_mappings(&prefix)?;
+            // Objects of nested looking namespaces such as `<namespace>.app` belong to those
+            os_ids.retain(|os_id| !os_id[1 + prefix.len()..].contains('.'));
+            os_ids
+        };
+
+        os_ids.sort();
+        Ok(os_ids)
+    }
+
+    /// Deletes every mapping in our namespace and returns how many were deleted
+    ///
+    /// This is meant to collect mappings leaked by crashed processes or previous test runs. Processes
+    /// that still have a deleted mapping open keep access to it, but it can no longer be opened.
+    #[cfg(not(target_os = "windows"))]
+    pub fn cleanup(&self) -> Result<usize, ShmemError> {
+        let os_ids = self.list()?;
+
+        let backend = self.get_backend();
+        for os_id in os_ids.iter() {
+            debug!("Deleting mapping {os_id}");
+            backend.unlink(&self.object_id(os_id)?)?;
+        }
+
+        if self.use_tmpfs {
+            // Only succeeds if no other process created a file in the meantime
+            let _ = std::fs::remove_dir(self.get_tmpfs_dir()?);
+        }
+        Ok(os_ids.len())
+    }
+}
@@ -0,0 +1,83 @@
+//! Validating and normalizing os_ids
+
+use std::path::Path;
+
+use crate::{ShmemConf, ShmemError};
+
+/// Longest file name accepted by the filesystems backing tmpfs mode and `shm_open()` (NAME_MAX)
+pub(crate) const MAX_NAME_LEN: usize = 255;
+
+/// Longest name accepted by `shm_open()`, including the leading `/` (PSHMNAMLEN)
+#[cfg(target_os = "macos")]
+pub(crate) const MAX_SHM_ID_LEN: usize = 31;
+#[cfg(not(target_os = "macos"))]
+pub(crate) const MAX_SHM_ID_LEN: usize = MAX_NAME_LEN + 1;
+
+impl ShmemConf {
+    /// Validates our os_id, if any, and brings it to the form expected by the current mode
+    ///
+    /// An os_id is a single name with an optional leading `/`. It must not be empty, `.` or `..`, contain
+    /// another `/`, a `\\` or a NUL byte, or be longer than NAME_MAX. For `shm_open()`, the leading `/` is added
+    /// if missing. In tmpfs mode, it is removed so the os_id is a file name within the tmpfs directory.
+    pub(crate) fn normalize_os_id(&mut self) -> Result<(), ShmemError> {
+        if let Some(ref os_id) = self.os_id {
+            self.os_id = Some(self.normalized(os_id)?);
+        }
+        Ok(())
+    }
+
+    /// Applies the os_id rules to `os_id` and returns its normalized form
+    fn normalized(&self, os_id: &str) -> Result<String, ShmemError> {
+        if self.ext.allows_raw_os_id() {
+            // Names of mappings that are not managed by this crate are used as-is
+            if os_id.is_empty() || os_id.contains('\0') {
+                return Err(ShmemError::InvalidOsId);
+            }
+            return Ok(String::from(os_id));
+        }
+
+        let name = os_id.strip_prefix('/').unwrap_or(os_id);
+        if name.is_empty()
+            || name == "."
+            || name == ".."
+            || name.len() > MAX_NAME_LEN
+            || name.contains(['/', '\\', '\0'])
+        {
+            return Err(ShmemError::InvalidOsId);
+        }
+
+        if self.use_tmpfs {
+            Ok(String::from(name))
+        } else if name.len() + 1 > MAX_SHM_ID_LEN {
+            Err(ShmemError::InvalidOsId)
+        } else {
+            Ok(format!("/{name}"))
+        }
+    }
+
+    /// Validates the identifier read from a flink and returns what should be opened
+    ///
+    /// In tmpfs mode, flinks contain the path of the file which must be in our tmpfs directory.
+    /// Both directories are canonicalized before being compared, so the creator and the opener may spell the
+    /// same directory differently (e.g. relative or through a symlink).
+    pub(crate) fn flink_target(&self, content: &str) -> Result<String, ShmemError> {
+        if !self.use_tmpfs {
+            return self.normalized(content);
+        }
+
+        let path = Path::new(content);
+        let name = path
+            .file_name()
+            .and_then(|v| v.to_str())
+            .ok_or(ShmemError::InvalidOsId)?;
+        let tmpfs_dir = self.get_tmpfs_dir()?;
+        let parent = path.parent().ok_or(ShmemError::InvalidOsId)?;
+        // A directory that cannot be resolved cannot be ours, the flink might also not be fully written yet
+        match (parent.canonicalize(), tmpfs_dir.canonicalize()) {
+            (Ok(parent), Ok(tmpfs_dir)) if parent == tmpfs_dir => {}
+            _ => return Err(ShmemError::InvalidOsId),
+        }
+        self.normalized(name)?;
+        Ok(String::from(content))
+    }
+}
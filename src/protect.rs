@@ -0,0 +1,102 @@
+//! Changing and tracking the memory protection of ranges of a mapping
+
+use std::ops::Range;
+
+#[cfg(not(target_os = "windows"))]
+use crate::{os_impl, Shmem, ShmemError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Access allowed to a range of a mapping
+pub enum Protection {
+    /// Any access faults
+    None,
+    /// Reads are allowed, writes fault
+    ReadOnly,
+    /// Reads and writes are allowed (the default)
+    ReadWrite,
+}
+
+#[derive(Default)]
+/// Keeps track of the ranges of a mapping that are not `ReadWrite`
+pub(crate) struct ProtectedRanges {
+    /// Sorted, non-overlapping ranges
+    ranges: Vec<(Range<usize>, Protection)>,
+}
+
+impl ProtectedRanges {
+    /// Records that `range` now has the protection `prot`
+    pub(crate) fn set(&mut self, range: Range<usize>, prot: Protection) {
+        let mut ranges = Vec::with_capacity(self.ranges.len() + 2);
+        for (r, p) in self.ranges.drain(..) {
+            // Keep the parts of existing ranges that are not covered by the new one
+            if r.start < range.start {
+                ranges.push((r.start..r.end.min(range.start), p));
+            }
+            if r.end > range.end {
+                ranges.push((r.start.max(range.end)..r.end, p));
+            }
+        }
+        if prot != Protection::ReadWrite {
+            ranges.push((range, prot));
+        }
+        ranges.retain(|(r, _)| !r.is_empty());
+        ranges.sort_by_key(|(r, _)| r.start);
+        self.ranges = ranges;
+    }
+
+    /// Returns the ranges that are not `ReadWrite`
+    #[cfg(not(target_os = "windows"))]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Range<usize>, Protection)> + '_ {
+        self.ranges.iter().cloned()
+    }
+
+    /// Returns whether any part of `range` has a protection other than `allowed`
+    ///
+    /// `ReadOnly` ranges are considered accessible when `allowed` is `ReadOnly`
+    pub(crate) fn restricts(&self, range: Range<usize>, allowed: Protection) -> bool {
+        self.ranges.iter().any(|(r, p)| {
+            r.start < range.end
+                && range.start < r.end
+                && !(allowed == Protection::ReadOnly && *p == Protection::ReadOnly)
+        })
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+impl Shmem {
+    /// Changes the access allowed to `range` of the mapping for the current process
+    ///
+    /// The start of the range must be page aligned and so must its end, unless it is the end of the
+    /// mapping. While part of the mapping is not `ReadWrite`, `as_slice_mut()` refuses to cover it.
+    pub fn protect(&mut self, range: Range<usize>, prot: Protection) -> Result<(), ShmemError> {
+        self.check_page_range(&range)?;
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let offset = self.config.data_offset();
+        self.mapping
+            .protect(offset + range.start, range.end - range.start, prot)?;
+        self.protected.set(range, prot);
+        Ok(())
+    }
+
+    /// Makes sure `range` lies within the mapping and is aligned to page boundaries
+    ///
+    /// The end of the mapping does not need to be aligned as the OS rounds up to the page size.
+    pub(crate) fn check_page_range(&self, range: &Range<usize>) -> Result<(), ShmemError> {
+        if range.start > range.end {
+            return Err(ShmemError::InvalidRange);
+        }
+        self.check_range(range.start, range.end - range.start)?;
+
+        let page_size = os_impl::page_size();
+        let offset = self.config.data_offset();
+        if (offset + range.start) % page_size != 0
+            || (range.end != self.len() && (offset + range.end) % page_size != 0)
+        {
+            return Err(ShmemError::UnalignedRange);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+//! Saving the contents of a mapping to a file and restoring them into a new mapping
+
+use std::convert::{TryFrom, TryInto};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::log::*;
+use crate::{Shmem, ShmemConf, ShmemError};
+
+/// Magic bytes at the start of every snapshot file
+const SNAPSHOT_MAGIC: [u8; 8] = *b"SHMSNAP\0";
+/// Bumped whenever the snapshot file format changes
+const SNAPSHOT_VERSION: u32 = 1;
+/// The snapshot contains a checksum of the data
+const FLAG_CHECKSUM: u32 = 1;
+/// magic + version + flags + layout_id + data_len + checksum + os_id length, followed by the os_id
+const SNAPSHOT_HEADER_LEN: usize = 8 + 4 + 4 + 8 + 8 + 8 + 4;
+/// Upper bound on the recorded os_id, anything longer means the file is not a snapshot
+const MAX_OS_ID_LEN: usize = 4096;
+/// Size of the chunks the data is copied out of the mapping in
+const COPY_CHUNK_LEN: usize = 64 * 1024;
+
+/// Lets users keep the mapping consistent while a snapshot copies it
+///
+/// This is typically implemented on top of a lock that lives in the shared memory itself.
+pub trait SnapshotLock {
+    /// Called right before the contents of the mapping are copied
+    fn lock(&self) -> Result<(), ShmemError>;
+    /// Called once the copy is done, even if it failed
+    fn unlock(&self);
+}
+
+#[derive(Clone, Copy, Default)]
+/// Struct used to configure how snapshots are taken and restored
+pub struct SnapshotConf<'a> {
+    checksum: bool,
+    layout_id: Option<u64>,
+    lock: Option<&'a dyn SnapshotLock>,
+}
+
+impl<'a> SnapshotConf<'a> {
+    /// Create a new default snapshot config
+    pub fn new() -> Self {
+        SnapshotConf::default()
+    }
+
+    /// Stores a checksum of the data in the snapshot, which is verified when restoring
+    pub fn checksum(mut self, enabled: bool) -> Self {
+        self.checksum = enabled;
+        self
+    }
+
+    /// Identifies the layout of the data in the mapping
+    ///
+    /// The id is recorded when taking a snapshot. When restoring, the snapshot must have been taken with
+    /// the same id.
+    pub fn layout_id(mut self, id: u64) -> Self {
+        self.layout_id = Some(id);
+        self
+    }
+
+    /// Holds `lock` while the contents of the mapping are being copied
+    pub fn lock(mut self, lock: &'a dyn SnapshotLock) -> Self {
+        self.lock = Some(lock);
+        self
+    }
+}
+
+/// Metadata recorded at the start of a snapshot file
+struct SnapshotHeader {
+    flags: u32,
+    layout_id: u64,
+    data_len: u64,
+    checksum: u64,
+    os_id: String,
+}
+
+impl SnapshotHeader {
+    fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        w.write_all(&SNAPSHOT_MAGIC)?;
+        w.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        w.write_all(&self.flags.to_le_bytes())?;
+        w.write_all(&self.layout_id.to_le_bytes())?;
+        w.write_all(&self.data_len.to_le_bytes())?;
+        w.write_all(&self.checksum.to_le_bytes())?;
+        w.write_all(&(self.os_id.len() as u32).to_le_bytes())?;
+        w.write_all(self.os_id.as_bytes())
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self, ShmemError> {
+        let mut buf = [0u8; SNAPSHOT_HEADER_LEN];
+        r.read_exact(&mut buf)
+            .map_err(ShmemError::SnapshotIoFailed)?;
+
+        let u32_at = |off: usize| u32::from_le_bytes(buf[off..off + 4].try_into().unwrap());
+        let u64_at = |off: usize| u64::from_le_bytes(buf[off..off + 8].try_into().unwrap());
+        if buf[..8] != SNAPSHOT_MAGIC || u32_at(8) != SNAPSHOT_VERSION {
+            return Err(ShmemError::SnapshotInvalid);
+        }
+
+        let os_id_len = u32_at(40) as usize;
+        if os_id_len > MAX_OS_ID_LEN {
+            return Err(ShmemError::SnapshotInvalid);
+        }
+        let mut os_id = vec![0u8; os_id_len];
+        r.read_exact(&mut os_id)
+            .map_err(ShmemError::SnapshotIoFailed)?;
+
+        Ok(SnapshotHeader {
+            flags: u32_at(12),
+            layout_id: u64_at(16),
+            data_len: u64_at(24),
+            checksum: u64_at(32),
+            os_id: String::from_utf8(os_id).map_err(|_| ShmemError::SnapshotInvalid)?,
+        })
+    }
+}
+
+/// Metadata of a snapshot file, as returned by `SnapshotInfo::read()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    /// The os_id of the mapping the snapshot was taken from
+    pub os_id: String,
+    /// The size of the data in the snapshot
+    pub len: usize,
+    /// The layout id the snapshot was taken with, 0 if none was set
+    pub layout_id: u64,
+    /// Whether the snapshot contains a checksum of its data
+    pub checksum: bool,
+}
+
+impl SnapshotInfo {
+    /// Reads the metadata at the start of a snapshot file without restoring it
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, ShmemError> {
+        let mut f = BufReader::new(File::open(path).map_err(ShmemError::SnapshotIoFailed)?);
+        let header = SnapshotHeader::read_from(&mut f)?;
+        Ok(SnapshotInfo {
+            len: usize::try_from(header.data_len).map_err(|_| ShmemError::SnapshotInvalid)?,
+            os_id: header.os_id,
+            layout_id: header.layout_id,
+            checksum: header.flags & FLAG_CHECKSUM != 0,
+        })
+    }
+}
+
+/// Incremental FNV-1a hash used to checksum snapshots and derive os_ids from keys
+pub(crate) struct Fnv1a(pub(crate) u64);
+impl Fnv1a {
+    pub(crate) fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        for b in data {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+impl Shmem {
+    /// Saves the contents of the mapping to a file
+    pub fn snapshot_to<P: AsRef<Path>>(&self, path: P) -> Result<(), ShmemError> {
+        self.snapshot_to_with(path, &SnapshotConf::new())
+    }
+
+    /// Saves the contents of the mapping to a file using a specific snapshot config
+    ///
+    /// The snapshot is written to a temporary file that is synced to disk before it replaces `path`.
+    pub fn snapshot_to_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        conf: &SnapshotConf,
+    ) -> Result<(), ShmemError> {
+        let path = path.as_ref();
+        debug!(
+            "Saving snapshot of '{}' to {}",
+            self.get_os_id(),
+            path.to_string_lossy()
+        );
+
+        // Write to a temporary file next to the destination so a failed snapshot never leaves a
+        // truncated file behind, and an existing snapshot is only replaced once the new one is complete
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(format!(".{}.tmp", std::process::id()));
+        let tmp_path = path.with_file_name(tmp_name);
+
+        let res = File::create(&tmp_path)
+            .map_err(ShmemError::SnapshotIoFailed)
+            .and_then(|f| self.write_snapshot(f, conf))
+            .and_then(|_| std::fs::rename(&tmp_path, path).map_err(ShmemError::SnapshotIoFailed));
+        if res.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        res?;
+        sync_parent_dir(path).map_err(ShmemError::SnapshotIoFailed)
+    }
+
+    fn write_snapshot(&self, f: File, conf: &SnapshotConf) -> Result<(), ShmemError> {
+        let mut f = BufWriter::new(f);
+        let mut header = SnapshotHeader {
+            flags: if conf.checksum { FLAG_CHECKSUM } else { 0 },
+            layout_id: conf.layout_id.unwrap_or(0),
+            data_len: self.len() as u64,
+            checksum: 0,
+            os_id: self.get_os_id().to_string(),
+        };
+        // Reserve room for the header, it is rewritten once the checksum is known
+        header
+            .write_to(&mut f)
+            .map_err(ShmemError::SnapshotIoFailed)?;
+
+        // The user provided lock (if any) is what keeps the contents consistent
+        if let Some(lock) = conf.lock {
+            lock.lock()?;
+        }
+        let mut hash = if conf.checksum {
+            Some(Fnv1a::new())
+        } else {
+            None
+        };
+        let res = self.write_data(&mut f, hash.as_mut());
+        if let Some(lock) = conf.lock {
+            lock.unlock();
+        }
+        res?;
+        if let Some(hash) = hash {
+            header.checksum = hash.0;
+        }
+
+        f.seek(SeekFrom::Start(0))
+            .and_then(|_| header.write_to(&mut f))
+            .and_then(|_| f.flush())
+            .and_then(|_| f.get_ref().sync_all())
+            .map_err(ShmemError::SnapshotIoFailed)
+    }
+
+    /// Writes the contents of the mapping to `f`, hashing exactly the bytes that were written
+    ///
+    /// The data is copied out of the mapping first, so concurrent writers cannot make the file and its
+    /// checksum disagree.
+    fn write_data<W: Write>(
+        &self,
+        f: &mut W,
+        mut hash: Option<&mut Fnv1a>,
+    ) -> Result<(), ShmemError> {
+        let mut buf = vec![0; COPY_CHUNK_LEN.min(self.len())];
+        let mut offset = 0;
+        while offset < self.len() {
+            let chunk_len = buf.len().min(self.len() - offset);
+            let chunk = &mut buf[..chunk_len];
+            self.copy_to(offset, chunk)?;
+            if let Some(ref mut hash) = hash {
+                hash.update(chunk);
+            }
+            f.write_all(chunk).map_err(ShmemError::SnapshotIoFailed)?;
+            offset += chunk_len;
+        }
+        Ok(())
+    }
+}
+
+/// Makes the rename of a snapshot durable
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened to be synced on Windows
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+impl ShmemConf {
+    /// Creates a new mapping holding the contents of a snapshot
+    ///
+    /// The size of the mapping is taken from the snapshot
+    pub fn create_from_snapshot<P: AsRef<Path>>(self, path: P) -> Result<Shmem, ShmemError> {
+        self.create_from_snapshot_with(path, &SnapshotConf::new())
+    }
+
+    /// Creates a new mapping holding the contents of a snapshot using a specific snapshot config
+    ///
+    /// If `conf` has a layout id, the snapshot must have been taken with the same id.
+    pub fn create_from_snapshot_with<P: AsRef<Path>>(
+        self,
+        path: P,
+        conf: &SnapshotConf,
+    ) -> Result<Shmem, ShmemError> {
+        let path = path.as_ref();
+        debug!("Restoring snapshot {}", path.to_string_lossy());
+
+        let mut f = BufReader::new(File::open(path).map_err(ShmemError::SnapshotIoFailed)?);
+        let header = SnapshotHeader::read_from(&mut f)?;
+        if let Some(layout_id) = conf.layout_id {
+            if layout_id != header.layout_id {
+                return Err(ShmemError::SnapshotLayoutMismatch);
+            }
+        }
+        let data_len = usize::try_from(header.data_len).map_err(|_| ShmemError::SnapshotInvalid)?;
+
+        let mut shmem = self.size(data_len).create()?;
+        // Safety: we just created the mapping, nobody else knows about it yet
+        let data = unsafe { &mut shmem.try_as_slice_mut()?[..data_len] };
+        f.read_exact(data).map_err(ShmemError::SnapshotIoFailed)?;
+
+        if header.flags & FLAG_CHECKSUM != 0 {
+            let mut hash = Fnv1a::new();
+            hash.update(data);
+            if hash.0 != header.checksum {
+                return Err(ShmemError::SnapshotChecksumMismatch);
+            }
+        }
+
+        Ok(shmem)
+    }
+}
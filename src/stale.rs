@@ -0,0 +1,75 @@
+//! Noticing when the name of a mapping is unlinked or reused
+
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::log::*;
+use crate::{Shmem, ShmemError};
+
+impl Shmem {
+    /// Returns whether the os_id of this mapping was unlinked or now refers to another mapping
+    ///
+    /// A stale mapping keeps working but nobody who opens the os_id will see its contents anymore.
+    /// This compares the device and inode of our mapping with the ones the os_id currently resolves to
+    /// (or the segment id for System V mappings). Mappings that cannot be opened by name fail with
+    /// `Unsupported`: anonymous and inherited mappings, and those of backends such as `MemfdBackend` whose
+    /// `ShmemBackend::can_open()` returns `false`. Outside of Linux, mappings using `owner_election()`
+    /// also fail with `Unsupported` unless their backend can look objects up without opening them, as
+    /// closing a descriptor of the object would release the locks the election relies on.
+    pub fn is_stale(&self) -> Result<bool, ShmemError> {
+        self.mapping.stale_check()?.is_stale()
+    }
+
+    /// Calls `callback` from a background thread once this mapping becomes stale
+    ///
+    /// The os_id is checked every `interval`, as with `is_stale()`. Consumers can use this to open the
+    /// os_id again when its owner replaced the mapping. Dropping the returned `StaleWatcher` stops
+    /// watching.
+    pub fn watch_stale<F: FnOnce() + Send + 'static>(
+        &self,
+        interval: Duration,
+        callback: F,
+    ) -> Result<StaleWatcher, ShmemError> {
+        let check = self.mapping.stale_check()?;
+        let (stop, stopped) = channel::<()>();
+
+        let thread = std::thread::spawn(move || loop {
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => return,
+            }
+            match check.is_stale() {
+                Ok(true) => {
+                    debug!("Mapping became stale");
+                    callback();
+                    return;
+                }
+                Ok(false) => {}
+                // The os_id may be unreachable for a moment, keep checking
+                Err(_e) => debug!("Failed to check whether the mapping is stale : {_e}"),
+            }
+        });
+
+        Ok(StaleWatcher {
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+}
+
+/// Watches a mapping in the background until dropped, see `Shmem::watch_stale()`
+pub struct StaleWatcher {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for StaleWatcher {
+    fn drop(&mut self) {
+        // Wakes the thread up right away
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
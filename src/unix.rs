@@ -1,26 +1,119 @@
 use std::num::NonZeroUsize;
-use std::os::fd::{FromRawFd, OwnedFd};
-use std::os::unix::fs::OpenOptionsExt;
-use std::os::unix::io::AsRawFd;
+use std::os::fd::OwnedFd;
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::ptr::{null_mut, NonNull};
+use std::sync::Arc;
 
 use crate::log::*;
-use nix::fcntl::OFlag;
-use nix::sys::mman::{mmap, munmap, shm_open, shm_unlink, MapFlags, ProtFlags};
+use nix::sys::mman::{mmap, mmap_anonymous, mprotect, msync, munmap, MapFlags, MsFlags, ProtFlags};
 use nix::sys::stat::{fstat, Mode};
-use nix::unistd::ftruncate;
 
-use crate::ShmemError;
+use crate::{
+    Advice, PosixShmBackend, Protection, ShmemBackend, ShmemConf, ShmemError, TmpfsBackend,
+};
+pub use liveness::LockByte;
+use liveness::{LivenessLock, LockKind};
+
+mod liveness;
+mod sysv;
+pub use sysv::{create_mapping_sysv, open_mapping_sysv};
 
 #[derive(Clone, Default)]
-pub struct ShmemConfExt;
+pub struct ShmemConfExt {
+    pub(crate) private: bool,
+    pub(crate) guard_pages: usize,
+    pub(crate) populate: bool,
+    pub(crate) mirrored: bool,
+    pub(crate) sysv_key: Option<libc::key_t>,
+    pub(crate) backend: Option<Arc<dyn ShmemBackend>>,
+}
+
+impl ShmemConf {
+    /// Whether our objects come from a backend set with `backend()`
+    #[cfg(feature = "serde")]
+    pub(crate) fn has_custom_backend(&self) -> bool {
+        self.ext.backend.is_some()
+    }
+
+    /// Writes to a private view never reach its mirror, and a mirror would wrap around to the header
+    /// instead of the start of the data
+    fn check_mirrored(&self) -> Result<(), ShmemError> {
+        if self.ext.mirrored && (self.ext.private || self.shared_ownership || self.owner_election) {
+            return Err(ShmemError::Unsupported);
+        }
+        Ok(())
+    }
+
+    /// Returns the backend that provides our objects
+    pub(crate) fn get_backend(&self) -> Arc<dyn ShmemBackend> {
+        match self.ext.backend {
+            Some(ref backend) => backend.clone(),
+            None if self.use_tmpfs => Arc::new(TmpfsBackend),
+            None => Arc::new(PosixShmBackend),
+        }
+    }
+
+    /// Creates and maps the object `unique_id` using our backend
+    pub(crate) fn create_object(&self, unique_id: &str) -> Result<MapData, ShmemError> {
+        self.check_mirrored()?;
+        create_mapping(
+            self.get_backend(),
+            unique_id,
+            self.size,
+            self.mode,
+            &self.ext,
+        )
+    }
+
+    /// Maps the existing object `unique_id` using our backend
+    pub(crate) fn open_object(&self, unique_id: &str) -> Result<MapData, ShmemError> {
+        self.check_mirrored()?;
+        open_mapping(self.get_backend(), unique_id, &self.ext)
+    }
+}
+
+impl ShmemConfExt {
+    /// Whether os_ids are passed to the OS without validation
+    pub(crate) fn allows_raw_os_id(&self) -> bool {
+        false
+    }
+
+    /// Key of the System V segment to use instead of a POSIX one
+    pub(crate) fn sysv_key(&self) -> Option<libc::key_t> {
+        self.sysv_key
+    }
+
+    /// Size in bytes of the guard region on each side of the mapping
+    fn guard_len(&self) -> Result<usize, ShmemError> {
+        self.guard_pages
+            .checked_mul(page_size())
+            .ok_or(ShmemError::MapSizeOverflow)
+    }
+
+    /// Flags used to map the shared memory into our address space
+    fn map_flags(&self) -> MapFlags {
+        #[allow(unused_mut)]
+        let mut flags = if self.private {
+            MapFlags::MAP_PRIVATE
+        } else {
+            MapFlags::MAP_SHARED
+        };
+        #[cfg(target_os = "linux")]
+        if self.populate {
+            flags |= MapFlags::MAP_POPULATE;
+        }
+        flags
+    }
+}
 
 pub struct MapData {
     //On linux, you must shm_unlink() the object created for the mapping. It wont disappear automatically.
     owner: bool,
 
-    //File descriptor to our open mapping
-    map_fd: OwnedFd,
+    //File descriptor to our open mapping, System V segments do not have one
+    map_fd: Option<OwnedFd>,
+    //System V segment we are attached to
+    sysv: Option<sysv::Segment>,
 
     //Shared mapping uid
     pub unique_id: String,
@@ -28,14 +121,90 @@ pub struct MapData {
     pub map_size: usize,
     //Pointer to the first address of our mapping
     pub map_ptr: *mut u8,
-    //Whether this mapping uses tmpfs (true) or shm_open (false)
-    is_tmpfs: bool,
+    //Where the object comes from, System V segments do not have a backend
+    backend: Option<Arc<dyn ShmemBackend>>,
+    //Whether the backing object outlives its owner
+    persistent: bool,
+    //Whether this is a private copy-on-write view (MAP_PRIVATE) of the mapping
+    private: bool,
+    //Size of the inaccessible region reserved on each side of the mapping
+    guard_len: usize,
+    //Whether a second view of the object directly follows the first one
+    mirrored: bool,
+    //Descriptor used for the byte-range locks of owner election and channels
+    liveness: Option<LivenessLock>,
 }
 
 impl MapData {
     pub fn as_mut_ptr(&self) -> *mut u8 {
         self.map_ptr
     }
+
+    /// Returns the file descriptor of the mapping, if it has one
+    pub fn as_raw_fd(&self) -> Option<RawFd> {
+        self.map_fd.as_ref().map(|v| v.as_raw_fd())
+    }
+}
+
+/// Remembers which object a mapping is attached to, to notice when its name goes away or is reused
+#[derive(Clone)]
+pub enum StaleCheck {
+    Object {
+        backend: Arc<dyn ShmemBackend>,
+        unique_id: String,
+        dev: libc::dev_t,
+        ino: libc::ino_t,
+    },
+    SysV(sysv::Segment),
+}
+
+impl StaleCheck {
+    /// Whether the name of the object was unlinked or now refers to another object
+    pub fn is_stale(&self) -> Result<bool, ShmemError> {
+        let (backend, unique_id, dev, ino) = match self {
+            StaleCheck::SysV(segment) => return segment.is_stale(),
+            StaleCheck::Object {
+                backend,
+                unique_id,
+                dev,
+                ino,
+            } => (backend, unique_id, *dev, *ino),
+        };
+
+        match backend.stat(unique_id) {
+            Ok(v) => Ok(v.st_dev != dev || v.st_ino != ino),
+            Err(ShmemError::MapOpenFailed(e)) if e == libc::ENOENT as u32 => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl MapData {
+    /// Returns what is needed to check whether this mapping became stale
+    ///
+    /// Anonymous and inherited mappings have no name to check against, nor do mappings of backends that
+    /// cannot open objects by name.
+    pub fn stale_check(&self) -> Result<StaleCheck, ShmemError> {
+        if let Some(segment) = self.sysv {
+            return Ok(StaleCheck::SysV(segment));
+        }
+        let backend = self.backend.clone().ok_or(ShmemError::Unsupported)?;
+        if !backend.can_open() {
+            return Err(ShmemError::Unsupported);
+        }
+        // Closing the descriptor opened by each check would release our liveness locks
+        #[cfg(not(target_os = "linux"))]
+        if self.liveness.is_some() && backend.stat_opens() {
+            return Err(ShmemError::Unsupported);
+        }
+        let stat = fstat(self.fd()?).map_err(|e| ShmemError::UnknownOsError(e as u32))?;
+        Ok(StaleCheck::Object {
+            backend,
+            unique_id: self.unique_id.clone(),
+            dev: stat.st_dev,
+            ino: stat.st_ino,
+        })
+    }
 }
 
 /// Shared memory teardown for linux
@@ -43,43 +212,50 @@ impl Drop for MapData {
     ///Takes care of properly closing the SharedMem (munmap(), shmem_unlink(), close())
     fn drop(&mut self) {
         //Unmap memory
-        if !self.map_ptr.is_null() {
-            trace!(
-                "munmap(map_ptr:{:p},map_size:{})",
-                self.map_ptr,
-                self.map_size
-            );
-            if let Err(_e) = unsafe {
-                munmap(
-                    NonNull::new_unchecked(self.map_ptr as *mut _),
-                    self.map_size,
-                )
-            } {
-                debug!("Failed to munmap() shared memory mapping : {}", _e);
+        if self.sysv.is_some() {
+            if !self.map_ptr.is_null() {
+                trace!("shmdt({:p})", self.map_ptr);
+                unsafe { libc::shmdt(self.map_ptr as *const _) };
+            }
+        } else if !self.map_ptr.is_null() {
+            // Include the mirror and the guard pages that surround our mapping
+            let unmap_ptr = unsafe { self.map_ptr.sub(self.guard_len) };
+            // Cannot fail, the same size was reserved when mapping
+            let unmap_size = reserved_len(self.map_size, self.guard_len, self.mirrored)
+                .map_or(0, NonZeroUsize::get);
+            trace!("munmap(map_ptr:{unmap_ptr:p},map_size:{unmap_size})");
+            if let Err(_e) =
+                unsafe { munmap(NonNull::new_unchecked(unmap_ptr as *mut _), unmap_size) }
+            {
+                debug!("Failed to munmap() shared memory mapping : {_e}");
             };
         }
 
         //Unlink shmem
-        if self.map_fd.as_raw_fd() != 0 {
+        if let Some(sysv::Segment { id, .. }) = self.sysv {
+            //remove the segment once everyone detached if we created it
+            if self.owner && !self.persistent {
+                debug!("Deleting System V segment");
+                trace!("shmctl({id}, IPC_RMID, NULL)");
+                if unsafe { libc::shmctl(id, libc::IPC_RMID, null_mut()) } != 0 {
+                    debug!(
+                        "Failed to shmctl(IPC_RMID) System V segment : {}",
+                        nix::Error::last()
+                    );
+                }
+            }
+        } else if let Some(ref _map_fd) = self.map_fd {
             //unlink shmem if we created it
-            if self.owner {
+            if self.owner && !self.persistent {
                 debug!("Deleting persistent mapping");
-                if self.is_tmpfs {
-                    // tmpfs mode: remove file
-                    trace!("remove_file({})", self.unique_id.as_str());
-                    if let Err(_e) = std::fs::remove_file(&self.unique_id) {
-                        debug!("Failed to remove tmpfs file {} : {}", self.unique_id, _e);
-                    };
-                } else {
-                    // shm_open mode: use shm_unlink
-                    trace!("shm_unlink({})", self.unique_id.as_str());
-                    if let Err(_e) = shm_unlink(self.unique_id.as_str()) {
-                        debug!("Failed to shm_unlink() shared memory : {}", _e);
+                if let Some(ref backend) = self.backend {
+                    if let Err(_e) = backend.unlink(&self.unique_id) {
+                        debug!("Failed to delete shared memory {} : {}", self.unique_id, _e);
                     };
                 }
             }
 
-            trace!("close({})", self.map_fd.as_raw_fd());
+            trace!("close({})", _map_fd.as_raw_fd());
             // Note: OwnedFd automatically closes on drop, so we don't need to call close
             // The file descriptor will be closed when self.map_fd is dropped
         }
@@ -87,74 +263,380 @@ impl Drop for MapData {
 }
 
 impl MapData {
+    /// Returns the file descriptor of the mapping, System V segments do not have one
+    fn fd(&self) -> Result<&OwnedFd, ShmemError> {
+        self.map_fd.as_ref().ok_or(ShmemError::Unsupported)
+    }
+
     pub fn set_owner(&mut self, is_owner: bool) -> bool {
         let prev_val = self.owner;
         self.owner = is_owner;
         prev_val
     }
+
+    /// Keeps the backing object around when the owner drops the mapping
+    pub fn set_persistent(&mut self, persistent: bool) {
+        self.persistent = persistent;
+    }
+
+    /// Discards the changes made to a private view so it reflects the shared contents again
+    pub fn resync_private(&mut self, ext: &ShmemConfExt) -> Result<(), ShmemError> {
+        if !self.private {
+            return Err(ShmemError::NotPrivate);
+        }
+
+        // Map the object again over our private view, with the same flags as when it was opened
+        let map_flags = ext.map_flags() | MapFlags::MAP_FIXED;
+        trace!(
+            "mmap({:p}, {}, {:X}, {:X}, {}, 0)",
+            self.map_ptr,
+            self.map_size,
+            ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+            map_flags,
+            self.fd()?.as_raw_fd(),
+        );
+        match unsafe {
+            mmap(
+                NonZeroUsize::new(self.map_ptr as usize),
+                NonZeroUsize::new_unchecked(self.map_size),
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                map_flags,
+                self.fd()?,
+                0,
+            )
+        } {
+            Ok(_) => Ok(()),
+            Err(e) => Err(ShmemError::UnknownOsError(e as u32)),
+        }
+    }
+
+    /// Flushes a range of the mapping to its backing object
+    pub fn flush(&self, offset: usize, len: usize, sync: bool) -> Result<(), ShmemError> {
+        // msync() requires a page aligned address
+        let page_offset = offset % page_size();
+        let addr = unsafe { self.map_ptr.add(offset - page_offset) };
+        let flags = if sync {
+            MsFlags::MS_SYNC
+        } else {
+            MsFlags::MS_ASYNC
+        };
+        trace!("msync({:p}, {}, {:?})", addr, len + page_offset, flags);
+        match unsafe {
+            msync(
+                NonNull::new_unchecked(addr as *mut _),
+                len + page_offset,
+                flags,
+            )
+        } {
+            Ok(_) => Ok(()),
+            Err(e) => Err(ShmemError::FlushFailed(e as u32)),
+        }
+    }
+
+    /// Changes the protection of a page aligned range of the mapping
+    pub fn protect(&self, offset: usize, len: usize, prot: Protection) -> Result<(), ShmemError> {
+        let flags = match prot {
+            Protection::None => ProtFlags::PROT_NONE,
+            Protection::ReadOnly => ProtFlags::PROT_READ,
+            Protection::ReadWrite => ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+        };
+        // The mirror must not give access to what the first view protects
+        let views = if self.mirrored { 2 } else { 1 };
+        for view in 0..views {
+            let addr = unsafe { self.map_ptr.add(view * self.map_size + offset) };
+            trace!("mprotect({addr:p}, {len}, {flags:?})");
+            if let Err(e) = unsafe { mprotect(NonNull::new_unchecked(addr as *mut _), len, flags) }
+            {
+                return Err(ShmemError::ProtectFailed(e as u32));
+            }
+        }
+        Ok(())
+    }
+
+    /// Passes advice about a page aligned range of the mapping to the OS
+    pub fn advise(&self, offset: usize, len: usize, advice: Advice) -> Result<(), ShmemError> {
+        let flag = match advice {
+            Advice::Sequential => libc::MADV_SEQUENTIAL,
+            Advice::Random => libc::MADV_RANDOM,
+            Advice::WillNeed => libc::MADV_WILLNEED,
+            Advice::DontNeed => libc::MADV_DONTNEED,
+            #[cfg(target_os = "linux")]
+            Advice::Remove => libc::MADV_REMOVE,
+            #[cfg(target_os = "linux")]
+            Advice::HugePage => libc::MADV_HUGEPAGE,
+            #[cfg(target_os = "linux")]
+            Advice::DontDump => libc::MADV_DONTDUMP,
+            #[cfg(target_os = "linux")]
+            Advice::DontFork => libc::MADV_DONTFORK,
+            #[cfg(not(target_os = "linux"))]
+            _ => return Err(ShmemError::Unsupported),
+        };
+        // Both views of a mirrored mapping have their own page tables
+        let views = if self.mirrored { 2 } else { 1 };
+        for view in 0..views {
+            let addr = unsafe { self.map_ptr.add(view * self.map_size + offset) };
+            let res = unsafe { libc::madvise(addr as *mut _, len, flag) };
+            trace!("madvise({addr:p}, {len}, {flag}) == {res}");
+            if res != 0 {
+                return Err(ShmemError::AdviseFailed(nix::Error::last() as u32));
+            }
+        }
+        Ok(())
+    }
+
+    /// Locks the mapping in RAM
+    pub fn lock(&self) -> Result<(), ShmemError> {
+        let res = unsafe { libc::mlock(self.map_ptr as *const _, self.map_size) };
+        trace!("mlock({:p}, {}) == {}", self.map_ptr, self.map_size, res);
+        if res == 0 {
+            return Ok(());
+        }
+        match nix::Error::last() {
+            // Unprivileged processes are limited by RLIMIT_MEMLOCK
+            e @ (nix::Error::ENOMEM | nix::Error::EPERM | nix::Error::EAGAIN) => {
+                let mut limit = libc::rlimit {
+                    rlim_cur: 0,
+                    rlim_max: 0,
+                };
+                if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0
+                    || limit.rlim_cur == libc::RLIM_INFINITY
+                {
+                    return Err(ShmemError::LockFailed(e as u32));
+                }
+                // rlim_t is not a u64 on every platform
+                #[allow(clippy::unnecessary_cast)]
+                Err(ShmemError::MemlockLimitExceeded(limit.rlim_cur as u64))
+            }
+            e => Err(ShmemError::LockFailed(e as u32)),
+        }
+    }
+
+    /// Allows the mapping to be paged out again
+    pub fn unlock(&self) -> Result<(), ShmemError> {
+        let res = unsafe { libc::munlock(self.map_ptr as *const _, self.map_size) };
+        trace!("munlock({:p}, {}) == {}", self.map_ptr, self.map_size, res);
+        if res != 0 {
+            return Err(ShmemError::LockFailed(nix::Error::last() as u32));
+        }
+        Ok(())
+    }
+
+    /// Opens the descriptor used for liveness locks, if not already done
+    pub fn open_liveness(&mut self) -> Result<(), ShmemError> {
+        if self.liveness.is_none() {
+            self.liveness = Some(LivenessLock::new(self.fd()?)?);
+        }
+        Ok(())
+    }
+
+    /// Returns the descriptor opened by `open_liveness()`
+    pub fn liveness(&self) -> Result<&LivenessLock, ShmemError> {
+        self.liveness.as_ref().ok_or(ShmemError::Unsupported)
+    }
+
+    /// Signals that we take part in owner election for as long as we are alive
+    pub fn attach(&self) -> Result<(), ShmemError> {
+        self.liveness()?.lock(LockByte::Attached, LockKind::Shared)
+    }
+
+    /// Stops taking part in owner election, returns whether no other process still does
+    pub fn detach(&self) -> Result<bool, ShmemError> {
+        let lock = self.liveness()?;
+        lock.unlock(LockByte::Attached);
+        // Whoever detaches last sees no other lock, others may race us but then one of them succeeds
+        lock.try_lock(LockByte::Attached, LockKind::Exclusive)
+    }
+
+    /// Tries to take the exclusive lock that signals the owner is alive
+    ///
+    /// The OS releases the lock if the owner dies.
+    pub fn try_lock_liveness(&self) -> Result<bool, ShmemError> {
+        self.liveness()?
+            .try_lock(LockByte::Owner, LockKind::Exclusive)
+    }
+
+    /// Takes the lock that signals the owner is alive, waiting for the current owner to release it
+    pub fn lock_liveness(&self) -> Result<(), ShmemError> {
+        self.liveness()?.lock(LockByte::Owner, LockKind::Exclusive)
+    }
+
+    /// Releases the lock taken by `try_lock_liveness()`
+    pub fn unlock_liveness(&self) {
+        if let Some(ref lock) = self.liveness {
+            lock.unlock(LockByte::Owner);
+        }
+    }
+
+    /// Tries to take the exclusive lock on `byte`, held until the mapping is dropped
+    #[cfg(feature = "channel")]
+    pub fn try_lock_byte(&self, byte: LockByte) -> Result<bool, ShmemError> {
+        self.liveness()?.try_lock(byte, LockKind::Exclusive)
+    }
+
+    /// Whether another process holds a lock on `byte`
+    #[cfg(feature = "channel")]
+    pub fn is_byte_locked(&self, byte: LockByte) -> Result<bool, ShmemError> {
+        self.liveness()?.is_locked(byte)
+    }
+}
+
+/// Returns the size of a memory page
+pub fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Rounds `size` up to a multiple of the page size
+fn round_to_page(size: usize) -> Result<usize, ShmemError> {
+    let page_size = page_size();
+    size.div_ceil(page_size)
+        .checked_mul(page_size)
+        .ok_or(ShmemError::MapSizeOverflow)
+}
+
+/// Returns the size of the address space taken by a mapping of `map_size` bytes, its mirror and the
+/// guard regions of `guard_len` bytes on each side
+fn reserved_len(
+    map_size: usize,
+    guard_len: usize,
+    mirrored: bool,
+) -> Result<NonZeroUsize, ShmemError> {
+    let views_len = if mirrored {
+        map_size.checked_mul(2).ok_or(ShmemError::MapSizeOverflow)?
+    } else {
+        round_to_page(map_size)?
+    };
+    guard_len
+        .checked_mul(2)
+        .and_then(|v| v.checked_add(views_len))
+        .ok_or(ShmemError::MapSizeOverflow)
+        .and_then(|v| NonZeroUsize::new(v).ok_or(ShmemError::MapSizeZero))
+}
+
+/// Maps `fd` read/write into our address space, or anonymous memory if there is no `fd`
+///
+/// When `guard_len` is not zero, the mapping is surrounded by that many bytes of inaccessible memory.
+/// When `mirrored` is set, `fd` is mapped a second time right after the first view, which requires
+/// `map_size` to be a multiple of the page size, and callers to check `reserved_len()` first.
+unsafe fn map_view(
+    fd: Option<&OwnedFd>,
+    map_size: NonZeroUsize,
+    flags: MapFlags,
+    guard_len: usize,
+    mirrored: bool,
+) -> nix::Result<NonNull<std::ffi::c_void>> {
+    let prot = ProtFlags::PROT_READ | ProtFlags::PROT_WRITE;
+    let map_at = |addr: Option<NonZeroUsize>, flags: MapFlags| match fd {
+        Some(fd) => mmap(addr, map_size, prot, flags, fd, 0),
+        None => mmap_anonymous(addr, map_size, prot, flags),
+    };
+    if guard_len == 0 && !mirrored {
+        return map_at(None, flags);
+    }
+
+    // Reserve the whole range as inaccessible memory and map the object in the middle of it
+    let reserved_size =
+        reserved_len(map_size.get(), guard_len, mirrored).map_err(|_| nix::Error::ENOMEM)?;
+    let reserved = mmap_anonymous(
+        None,
+        reserved_size,
+        ProtFlags::PROT_NONE,
+        MapFlags::MAP_PRIVATE,
+    )?;
+    trace!(
+        "mmap(NULL, {reserved_size}, PROT_NONE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0) == {reserved:p}"
+    );
+    let addr = (reserved.as_ptr() as *mut u8).add(guard_len);
+    let mut res = map_at(
+        NonZeroUsize::new(addr as usize),
+        flags | MapFlags::MAP_FIXED,
+    );
+    if mirrored && res.is_ok() {
+        let mirror = addr.add(map_size.get());
+        res = map_at(
+            NonZeroUsize::new(mirror as usize),
+            flags | MapFlags::MAP_FIXED,
+        )
+        .map(|_| NonNull::new_unchecked(addr as *mut _));
+        trace!("mmap({mirror:p}, {map_size}, ...) mirror of {addr:p}");
+    }
+    if res.is_err() {
+        let _ = munmap(reserved, reserved_size.get());
+    }
+    res
+}
+
+/// Lists the shared memory objects whose name starts with `prefix`, as `/<name>`
+#[cfg(target_os = "linux")]
+pub fn list_mappings(prefix: &str) -> Result<Vec<String>, ShmemError> {
+    // shm_open() objects live in /dev/shm on linux
+    let dir = std::fs::read_dir("/dev/shm").map_err(ShmemError::ListFailed)?;
+    let mut unique_ids = Vec::new();
+    for entry in dir {
+        let entry = entry.map_err(ShmemError::ListFailed)?;
+        if let Some(name) = entry.file_name().to_str() {
+            if name.starts_with(prefix) {
+                unique_ids.push(format!("/{name}"));
+            }
+        }
+    }
+    Ok(unique_ids)
+}
+
+/// Lists the shared memory objects whose name starts with `prefix`, as `/<name>`
+#[cfg(not(target_os = "linux"))]
+pub fn list_mappings(_prefix: &str) -> Result<Vec<String>, ShmemError> {
+    // There is no way to enumerate shm_open() objects
+    Err(ShmemError::Unsupported)
 }
 
 /// Creates a mapping specified by the uid and size
 pub fn create_mapping(
+    backend: Arc<dyn ShmemBackend>,
     unique_id: &str,
     map_size: usize,
     mode: Option<Mode>,
+    ext: &ShmemConfExt,
 ) -> Result<MapData, ShmemError> {
     //Create shared memory file descriptor
-    debug!("Creating persistent mapping at {}", unique_id);
+    debug!("Creating persistent mapping at {unique_id}");
 
+    // The mirror has to start on a page boundary
+    let map_size = if ext.mirrored {
+        round_to_page(map_size)?
+    } else {
+        map_size
+    };
     let nz_map_size = NonZeroUsize::new(map_size).ok_or(ShmemError::MapSizeZero)?;
+    let guard_len = ext.guard_len()?;
+    reserved_len(map_size, guard_len, ext.mirrored)?;
     let mode = mode.unwrap_or(Mode::S_IRUSR | Mode::S_IWUSR);
-    let shmem_fd = match shm_open(
-        unique_id, //Unique name that usualy pops up in /dev/shm/
-        OFlag::O_CREAT | OFlag::O_EXCL | OFlag::O_RDWR, //create exclusively (error if collision) and read/write to allow resize
-        mode,                                           // default permission allow user+rw
-    ) {
-        Ok(v) => {
-            trace!(
-                "shm_open({}, {:X}, {:X}) == {}",
-                unique_id,
-                OFlag::O_CREAT | OFlag::O_EXCL | OFlag::O_RDWR,
-                mode,
-                v.as_raw_fd()
-            );
-            v
-        }
-        Err(nix::Error::EEXIST) => return Err(ShmemError::MappingIdExists),
-        Err(e) => return Err(ShmemError::MapCreateFailed(e as u32)),
-    };
+    let shmem_fd = backend.create(unique_id, map_size, mode)?;
 
     let mut new_map: MapData = MapData {
         owner: true,
         unique_id: String::from(unique_id),
-        map_fd: shmem_fd,
+        map_fd: Some(shmem_fd),
+        sysv: None,
+        backend: Some(backend),
         map_size,
         map_ptr: null_mut(),
-        is_tmpfs: false,
-    };
-
-    //Enlarge the memory descriptor file size to the requested map size
-    debug!("Creating memory mapping");
-    trace!(
-        "ftruncate({}, {})",
-        new_map.map_fd.as_raw_fd(),
-        new_map.map_size
-    );
-    match ftruncate(&new_map.map_fd, new_map.map_size as _) {
-        Ok(_) => {}
-        Err(e) => return Err(ShmemError::UnknownOsError(e as u32)),
+        persistent: false,
+        private: false,
+        guard_len,
+        mirrored: ext.mirrored,
+        liveness: None,
     };
 
     //Put the mapping in our address space
     debug!("Loading mapping into address space");
+    let map_flags = ext.map_flags();
     new_map.map_ptr = match unsafe {
-        mmap(
-            None,                                         //Desired addr
-            nz_map_size,                                  //size of mapping
-            ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, //Permissions on pages
-            MapFlags::MAP_SHARED,                         //What kind of mapping
-            &new_map.map_fd,                              //fd
-            0,                                            //Offset into fd
+        map_view(
+            Some(new_map.fd()?),
+            nz_map_size,
+            map_flags,
+            new_map.guard_len,
+            new_map.mirrored,
         )
     } {
         Ok(v) => {
@@ -162,8 +644,8 @@ pub fn create_mapping(
                 "mmap(NULL, {}, {:X}, {:X}, {}, 0) == {:p}",
                 new_map.map_size,
                 ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
-                MapFlags::MAP_SHARED,
-                new_map.map_fd.as_raw_fd(),
+                map_flags,
+                new_map.fd()?.as_raw_fd(),
                 v
             );
             v.as_ptr() as *mut u8
@@ -176,57 +658,60 @@ pub fn create_mapping(
 
 /// Opens an existing mapping specified by its uid
 pub fn open_mapping(
+    backend: Arc<dyn ShmemBackend>,
     unique_id: &str,
-    _map_size: usize,
-    _ext: &ShmemConfExt,
+    ext: &ShmemConfExt,
 ) -> Result<MapData, ShmemError> {
     //Open shared memory
-    debug!("Openning persistent mapping at {}", unique_id);
-    let shmem_fd = match shm_open(
-        unique_id,
-        OFlag::O_RDWR, //Open read write
-        Mode::S_IRUSR,
-    ) {
-        Ok(v) => {
-            trace!(
-                "shm_open({}, {:X}, {:X}) == {}",
-                unique_id,
-                OFlag::O_RDWR,
-                Mode::S_IRUSR,
-                v.as_raw_fd()
-            );
-            v
-        }
-        Err(e) => return Err(ShmemError::MapOpenFailed(e as u32)),
-    };
+    debug!("Openning persistent mapping at {unique_id}");
+    let shmem_fd = backend.open(unique_id)?;
+    open_mapping_fd(shmem_fd, Some(backend), unique_id, ext)
+}
 
+/// Maps an already open object, which is only deleted through `backend` if we become its owner
+pub fn open_mapping_fd(
+    shmem_fd: OwnedFd,
+    backend: Option<Arc<dyn ShmemBackend>>,
+    unique_id: &str,
+    ext: &ShmemConfExt,
+) -> Result<MapData, ShmemError> {
     let mut new_map: MapData = MapData {
         owner: false,
         unique_id: String::from(unique_id),
-        map_fd: shmem_fd,
+        map_fd: Some(shmem_fd),
+        sysv: None,
+        backend,
         map_size: 0,
         map_ptr: null_mut(),
-        is_tmpfs: false,
+        persistent: false,
+        private: ext.private,
+        guard_len: ext.guard_len()?,
+        mirrored: ext.mirrored,
+        liveness: None,
     };
 
     //Get mmap size
-    new_map.map_size = match fstat(&new_map.map_fd) {
+    new_map.map_size = match fstat(new_map.fd()?) {
         Ok(v) => v.st_size as usize,
         Err(e) => return Err(ShmemError::MapOpenFailed(e as u32)),
     };
 
     let nz_map_size = NonZeroUsize::new(new_map.map_size).ok_or(ShmemError::MapSizeZero)?;
+    if new_map.mirrored && new_map.map_size % page_size() != 0 {
+        return Err(ShmemError::MapSizeMismatch(new_map.map_size));
+    }
+    reserved_len(new_map.map_size, new_map.guard_len, new_map.mirrored)?;
 
     //Map memory into our address space
     debug!("Loading mapping into address space");
+    let map_flags = ext.map_flags();
     new_map.map_ptr = match unsafe {
-        mmap(
-            None,                                         //Desired addr
-            nz_map_size,                                  //size of mapping
-            ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, //Permissions on pages
-            MapFlags::MAP_SHARED,                         //What kind of mapping
-            &new_map.map_fd,                              //fd
-            0,                                            //Offset into fd
+        map_view(
+            Some(new_map.fd()?),
+            nz_map_size,
+            map_flags,
+            new_map.guard_len,
+            new_map.mirrored,
         )
     } {
         Ok(v) => {
@@ -234,8 +719,8 @@ pub fn open_mapping(
                 "mmap(NULL, {}, {:X}, {:X}, {}, 0) == {:p}",
                 new_map.map_size,
                 ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
-                MapFlags::MAP_SHARED,
-                new_map.map_fd.as_raw_fd(),
+                map_flags,
+                new_map.fd()?.as_raw_fd(),
                 v
             );
             v.as_ptr() as *mut u8
@@ -246,67 +731,28 @@ pub fn open_mapping(
     Ok(new_map)
 }
 
-/// Creates a mapping using tmpfs file
-pub fn create_mapping_tmpfs(
-    file_path: &str,
+/// Creates an anonymous mapping that is only shared with the processes we fork
+pub fn create_mapping_anonymous(
     map_size: usize,
-    mode: Option<Mode>,
+    ext: &ShmemConfExt,
 ) -> Result<MapData, ShmemError> {
+    // There is no object to map a second time
+    if ext.private || ext.mirrored {
+        return Err(ShmemError::Unsupported);
+    }
     let nz_map_size = NonZeroUsize::new(map_size).ok_or(ShmemError::MapSizeZero)?;
-    let mode_bits = mode.unwrap_or(Mode::S_IRUSR | Mode::S_IWUSR).bits();
+    let guard_len = ext.guard_len()?;
+    reserved_len(map_size, guard_len, false)?;
 
-    debug!("Creating tmpfs mapping at {}", file_path);
-
-    // Create the file
-    let file = std::fs::OpenOptions::new()
-        .create_new(true)
-        .read(true)
-        .write(true)
-        .mode({
-            #[cfg(target_os = "macos")]
-            {
-                u32::from(mode_bits)
-            }
-            #[cfg(not(target_os = "macos"))]
-            {
-                mode_bits
-            }
-        })
-        .open(file_path)
-        .map_err(|e| match e.kind() {
-            std::io::ErrorKind::AlreadyExists => ShmemError::MappingIdExists,
-            _ => ShmemError::MapCreateFailed(e.raw_os_error().unwrap_or(0) as u32),
-        })?;
-
-    let fd = file.as_raw_fd();
-    let owned_fd = unsafe { OwnedFd::from_raw_fd(fd) };
-
-    // Set file size
-    trace!("ftruncate({}, {})", fd, map_size);
-    match ftruncate(&owned_fd, map_size as _) {
-        Ok(_) => {}
-        Err(e) => return Err(ShmemError::UnknownOsError(e as u32)),
-    }
-
-    // Map the file into memory
-    debug!("Loading tmpfs mapping into address space");
-    let map_ptr = match unsafe {
-        mmap(
-            None,                                         // Desired addr
-            nz_map_size,                                  // Size of mapping
-            ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, // Permissions on pages
-            MapFlags::MAP_SHARED,                         // What kind of mapping
-            &owned_fd,                                    // File descriptor
-            0,                                            // Offset into fd
-        )
-    } {
+    debug!("Creating anonymous mapping");
+    let map_flags = ext.map_flags();
+    let map_ptr = match unsafe { map_view(None, nz_map_size, map_flags, guard_len, false) } {
         Ok(v) => {
             trace!(
-                "mmap(NULL, {}, {:X}, {:X}, {}, 0) == {:p}",
+                "mmap(NULL, {}, {:X}, {:X}|MAP_ANONYMOUS, -1, 0) == {:p}",
                 map_size,
                 ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
-                MapFlags::MAP_SHARED,
-                owned_fd.as_raw_fd(),
+                map_flags,
                 v
             );
             v.as_ptr() as *mut u8
@@ -314,78 +760,19 @@ pub fn create_mapping_tmpfs(
         Err(e) => return Err(ShmemError::MapCreateFailed(e as u32)),
     };
 
-    // Prevent file from being dropped and closed
-    std::mem::forget(file);
-
+    // Without a name or backend, dropping the mapping only unmaps it from each process
     Ok(MapData {
         owner: true,
-        unique_id: String::from(file_path),
-        map_fd: owned_fd,
-        map_size,
-        map_ptr,
-        is_tmpfs: true,
-    })
-}
-
-/// Opens an existing tmpfs mapping
-pub fn open_mapping_tmpfs(file_path: &str, _expected_size: usize) -> Result<MapData, ShmemError> {
-    use std::os::unix::io::AsRawFd;
-
-    debug!("Opening tmpfs mapping at {}", file_path);
-
-    // Open the file
-    let file = std::fs::OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open(file_path)
-        .map_err(|e| ShmemError::MapOpenFailed(e.raw_os_error().unwrap_or(0) as u32))?;
-
-    let fd = file.as_raw_fd();
-    let owned_fd = unsafe { OwnedFd::from_raw_fd(fd) };
-
-    // Get file size
-    let map_size = match fstat(&owned_fd) {
-        Ok(v) => v.st_size as usize,
-        Err(e) => return Err(ShmemError::MapOpenFailed(e as u32)),
-    };
-
-    let nz_map_size = NonZeroUsize::new(map_size).ok_or(ShmemError::MapSizeZero)?;
-
-    // Map the file into memory
-    debug!("Loading tmpfs mapping into address space");
-    let map_ptr = match unsafe {
-        mmap(
-            None,                                         // Desired addr
-            nz_map_size,                                  // Size of mapping
-            ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, // Permissions on pages
-            MapFlags::MAP_SHARED,                         // What kind of mapping
-            &owned_fd,                                    // File descriptor
-            0,                                            // Offset into fd
-        )
-    } {
-        Ok(v) => {
-            trace!(
-                "mmap(NULL, {}, {:X}, {:X}, {}, 0) == {:p}",
-                map_size,
-                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
-                MapFlags::MAP_SHARED,
-                owned_fd.as_raw_fd(),
-                v
-            );
-            v.as_ptr() as *mut u8
-        }
-        Err(e) => return Err(ShmemError::MapOpenFailed(e as u32)),
-    };
-
-    // Prevent file from being dropped and closed
-    std::mem::forget(file);
-
-    Ok(MapData {
-        owner: false,
-        unique_id: String::from(file_path),
-        map_fd: owned_fd,
+        unique_id: String::new(),
+        map_fd: None,
+        sysv: None,
+        backend: None,
         map_size,
         map_ptr,
-        is_tmpfs: true,
+        persistent: false,
+        private: false,
+        guard_len,
+        mirrored: false,
+        liveness: None,
     })
 }
@@ -0,0 +1,302 @@
+//! Byte-range locks telling which processes are still using a mapping
+//!
+//! The OS releases the locks of a process when it exits, however it exits, which makes them a reliable
+//! way to notice dead processes without relying on pids that can be recycled or belong to another pid
+//! namespace. Each `MapData` that needs them opens its own descriptor of the object to lock.
+//!
+//! On Linux, open file description locks are used: they belong to that descriptor, so two mappings of
+//! the same object in one process see each other's locks. The descriptor is replaced in children created
+//! with `fork()` so they never keep the locks of their parent alive. Other platforms use classic record
+//! locks, which belong to the whole process: they are not inherited by children but a process does not
+//! see its own locks, and closing any descriptor of the object releases them. There, the crate never
+//! opens an object it holds locks on just to inspect it: `Shmem::is_stale()` fails with `Unsupported`
+//! unless the backend can look the object up without opening it (see `ShmemBackend::stat_opens()`).
+//! Users must avoid opening the object elsewhere in the process, including with `ShmemBackend::info()`.
+
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+use crate::log::*;
+use crate::ShmemError;
+
+#[cfg(target_os = "linux")]
+const SETLK: libc::c_int = libc::F_OFD_SETLK;
+#[cfg(target_os = "linux")]
+const SETLKW: libc::c_int = libc::F_OFD_SETLKW;
+#[cfg(all(feature = "channel", target_os = "linux"))]
+const GETLK: libc::c_int = libc::F_OFD_GETLK;
+#[cfg(not(target_os = "linux"))]
+const SETLK: libc::c_int = libc::F_SETLK;
+#[cfg(not(target_os = "linux"))]
+const SETLKW: libc::c_int = libc::F_SETLKW;
+#[cfg(all(feature = "channel", not(target_os = "linux")))]
+const GETLK: libc::c_int = libc::F_GETLK;
+
+/// The bytes of the object that are locked, each with its own meaning
+#[derive(Debug, Clone, Copy)]
+pub enum LockByte {
+    /// Write locked by the owner for as long as it is alive (owner election)
+    Owner = 0,
+    /// Read locked by every process attached with owner election
+    Attached = 1,
+    /// Write locked by the process holding the sending half of a channel
+    #[cfg(feature = "channel")]
+    Sender = 2,
+    /// Write locked by the process holding the receiving half of a channel
+    #[cfg(feature = "channel")]
+    Receiver = 3,
+}
+
+/// Kinds of locks, matching the `F_*LCK` constants
+#[derive(Debug, Clone, Copy)]
+pub enum LockKind {
+    Shared,
+    Exclusive,
+    Unlock,
+}
+
+impl LockKind {
+    fn as_raw(self) -> libc::c_short {
+        (match self {
+            LockKind::Shared => libc::F_RDLCK,
+            LockKind::Exclusive => libc::F_WRLCK,
+            LockKind::Unlock => libc::F_UNLCK,
+        }) as libc::c_short
+    }
+}
+
+/// A descriptor of the mapped object dedicated to byte-range locks
+pub struct LivenessLock {
+    fd: OwnedFd,
+    /// Process that opened the descriptor, the locks mean nothing in its forked children
+    pid: u32,
+}
+
+impl LivenessLock {
+    /// Opens a new descriptor of the object behind `map_fd`
+    pub fn new(map_fd: &OwnedFd) -> Result<Self, ShmemError> {
+        let fd = fork_guard::register(|| reopen(map_fd))?;
+        Ok(LivenessLock {
+            fd,
+            pid: std::process::id(),
+        })
+    }
+
+    /// Fails with `Unsupported` in a child forked from the process that opened the descriptor
+    fn check_process(&self) -> Result<(), ShmemError> {
+        if std::process::id() != self.pid {
+            return Err(ShmemError::Unsupported);
+        }
+        Ok(())
+    }
+
+    /// Applies `kind` to `byte` with `cmd`, returns the lock description as updated by the OS
+    fn fcntl(
+        &self,
+        cmd: libc::c_int,
+        kind: LockKind,
+        byte: LockByte,
+    ) -> Result<libc::flock, nix::Error> {
+        // Safety: flock is plain old data
+        let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+        lock.l_type = kind.as_raw();
+        lock.l_whence = libc::SEEK_SET as libc::c_short;
+        lock.l_start = byte as libc::off_t;
+        lock.l_len = 1;
+        let res = unsafe { libc::fcntl(self.fd.as_raw_fd(), cmd, &mut lock) };
+        trace!(
+            "fcntl({}, {cmd}, {kind:?} {byte:?}) == {res}",
+            self.fd.as_raw_fd()
+        );
+        if res == -1 {
+            return Err(nix::Error::last());
+        }
+        Ok(lock)
+    }
+
+    /// Takes a lock on `byte` without waiting, returns whether it was taken
+    pub fn try_lock(&self, byte: LockByte, kind: LockKind) -> Result<bool, ShmemError> {
+        self.check_process()?;
+        match self.fcntl(SETLK, kind, byte) {
+            Ok(_) => Ok(true),
+            Err(nix::Error::EAGAIN) | Err(nix::Error::EACCES) => Ok(false),
+            Err(e) => Err(ShmemError::UnknownOsError(e as u32)),
+        }
+    }
+
+    /// Takes a lock on `byte`, waiting for conflicting locks to be released
+    pub fn lock(&self, byte: LockByte, kind: LockKind) -> Result<(), ShmemError> {
+        self.check_process()?;
+        loop {
+            match self.fcntl(SETLKW, kind, byte) {
+                Ok(_) => return Ok(()),
+                Err(nix::Error::EINTR) => continue,
+                Err(e) => return Err(ShmemError::UnknownOsError(e as u32)),
+            }
+        }
+    }
+
+    /// Releases our lock on `byte`, if any
+    pub fn unlock(&self, byte: LockByte) {
+        if self.check_process().is_ok() {
+            let _ = self.fcntl(SETLK, LockKind::Unlock, byte);
+        }
+    }
+
+    /// Whether another process (or another descriptor on Linux) holds a lock on `byte`
+    #[cfg(feature = "channel")]
+    pub fn is_locked(&self, byte: LockByte) -> Result<bool, ShmemError> {
+        self.check_process()?;
+        match self.fcntl(GETLK, LockKind::Exclusive, byte) {
+            Ok(lock) => Ok(lock.l_type != LockKind::Unlock.as_raw()),
+            Err(e) => Err(ShmemError::UnknownOsError(e as u32)),
+        }
+    }
+}
+
+impl Drop for LivenessLock {
+    fn drop(&mut self) {
+        // Forget the descriptor before it is closed and its number reused
+        fork_guard::unregister(self.fd.as_raw_fd());
+    }
+}
+
+/// Opens a descriptor with its own open file description, for its own set of locks
+#[cfg(target_os = "linux")]
+fn reopen(map_fd: &OwnedFd) -> Result<OwnedFd, ShmemError> {
+    // Works for every kind of object, including memfds and objects whose name was unlinked or reused
+    let path = std::ffi::CString::new(format!("/proc/self/fd/{}", map_fd.as_raw_fd())).unwrap();
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDWR | libc::O_CLOEXEC) };
+    trace!("open({path:?}, O_RDWR|O_CLOEXEC) == {fd}");
+    if fd == -1 {
+        return Err(ShmemError::UnknownOsError(nix::Error::last() as u32));
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Classic record locks belong to the process, any descriptor of the object will do
+#[cfg(not(target_os = "linux"))]
+fn reopen(map_fd: &OwnedFd) -> Result<OwnedFd, ShmemError> {
+    map_fd
+        .try_clone()
+        .map_err(|e| ShmemError::UnknownOsError(e.raw_os_error().unwrap_or(0) as u32))
+}
+
+/// Keeps forked children from holding on to the open file descriptions we lock
+///
+/// Children share the open file descriptions of their parent, which keeps open file description locks
+/// alive after the parent exits. A `pthread_atfork()` handler replaces the registered descriptors with
+/// `/dev/null` in the child.
+#[cfg(target_os = "linux")]
+mod fork_guard {
+    use std::cell::UnsafeCell;
+    use std::os::fd::{OwnedFd, RawFd};
+    use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+    use std::sync::Once;
+
+    use crate::ShmemError;
+
+    /// A spinlock, as `fork()` handlers cannot release a `Mutex` guard they do not hold
+    struct Registry {
+        locked: AtomicBool,
+        fds: UnsafeCell<Vec<RawFd>>,
+    }
+
+    // Safety: `fds` is only accessed while holding `locked`
+    unsafe impl Sync for Registry {}
+
+    static REGISTRY: Registry = Registry {
+        locked: AtomicBool::new(false),
+        fds: UnsafeCell::new(Vec::new()),
+    };
+    /// Descriptor of /dev/null, opened once
+    static DEV_NULL: AtomicI32 = AtomicI32::new(-1);
+    static INIT: Once = Once::new();
+
+    fn acquire() {
+        while REGISTRY
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+    }
+
+    fn release() {
+        REGISTRY.locked.store(false, Ordering::Release);
+    }
+
+    extern "C" fn prepare() {
+        // Nobody registers a descriptor the child would not know about while we fork
+        acquire();
+    }
+
+    extern "C" fn parent() {
+        release();
+    }
+
+    extern "C" fn child() {
+        // Safety: we hold the lock since prepare(), and only async-signal-safe calls are made
+        let fds = unsafe { &*REGISTRY.fds.get() };
+        let dev_null = DEV_NULL.load(Ordering::Relaxed);
+        for fd in fds.iter() {
+            unsafe { libc::dup3(dev_null, *fd, libc::O_CLOEXEC) };
+        }
+        release();
+    }
+
+    /// Opens a descriptor with `open` and registers it, so no fork can happen in between
+    pub fn register(
+        open: impl FnOnce() -> Result<OwnedFd, ShmemError>,
+    ) -> Result<OwnedFd, ShmemError> {
+        INIT.call_once(|| {
+            let fd = unsafe {
+                libc::open(
+                    b"/dev/null\0".as_ptr() as *const libc::c_char,
+                    libc::O_RDWR | libc::O_CLOEXEC,
+                )
+            };
+            if fd != -1
+                && unsafe { libc::pthread_atfork(Some(prepare), Some(parent), Some(child)) } == 0
+            {
+                DEV_NULL.store(fd, Ordering::Relaxed);
+            }
+        });
+        if DEV_NULL.load(Ordering::Relaxed) == -1 {
+            return Err(ShmemError::Unsupported);
+        }
+
+        acquire();
+        let res = open();
+        if let Ok(ref fd) = res {
+            // Safety: we hold the lock
+            unsafe { &mut *REGISTRY.fds.get() }.push(std::os::fd::AsRawFd::as_raw_fd(fd));
+        }
+        release();
+        res
+    }
+
+    /// Stops replacing `fd` in forked children
+    pub fn unregister(fd: RawFd) {
+        acquire();
+        // Safety: we hold the lock
+        unsafe { &mut *REGISTRY.fds.get() }.retain(|v| *v != fd);
+        release();
+    }
+}
+
+/// Classic record locks are not inherited by children
+#[cfg(not(target_os = "linux"))]
+mod fork_guard {
+    use std::os::fd::{OwnedFd, RawFd};
+
+    use crate::ShmemError;
+
+    pub fn register(
+        open: impl FnOnce() -> Result<OwnedFd, ShmemError>,
+    ) -> Result<OwnedFd, ShmemError> {
+        open()
+    }
+
+    pub fn unregister(_fd: RawFd) {}
+}
@@ -0,0 +1,148 @@
+//! System V shared memory segments (`shmget()`/`shmat()`)
+
+use std::mem::MaybeUninit;
+use std::ptr::null_mut;
+
+use nix::sys::stat::Mode;
+
+use super::{MapData, ShmemConfExt};
+use crate::log::*;
+use crate::{Shmem, ShmemError};
+
+impl Shmem {
+    /// Returns how many times the System V segment is currently attached, across all processes
+    pub fn attach_count(&self) -> Result<usize, ShmemError> {
+        let segment = self.mapping.sysv.ok_or(ShmemError::Unsupported)?;
+        Ok(segment_info(segment.id)?.shm_nattch as usize)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Identifies the System V segment a mapping is attached to
+pub struct Segment {
+    pub key: libc::key_t,
+    pub id: libc::c_int,
+}
+
+impl Segment {
+    /// Whether the segment was removed or its key now refers to another segment
+    pub fn is_stale(&self) -> Result<bool, ShmemError> {
+        if self.key == libc::IPC_PRIVATE {
+            // There is no key to look the segment up with
+            return Err(ShmemError::Unsupported);
+        }
+        let id = unsafe { libc::shmget(self.key, 0, 0) };
+        trace!("shmget({:#x}, 0, 0) == {id}", self.key);
+        if id == -1 {
+            return match nix::Error::last() {
+                nix::Error::ENOENT => Ok(true),
+                e => Err(ShmemError::UnknownOsError(e as u32)),
+            };
+        }
+        Ok(id != self.id)
+    }
+}
+
+/// Makes sure the requested options can be honored with `shmat()`
+///
+/// Owner election relies on a lock taken on the file descriptor of the mapping, which segments do not have.
+fn check_ext(ext: &ShmemConfExt, owner_election: bool) -> Result<(), ShmemError> {
+    if ext.private || ext.guard_pages != 0 || ext.mirrored || ext.populate || owner_election {
+        return Err(ShmemError::Unsupported);
+    }
+    Ok(())
+}
+
+/// Returns the `shmctl(IPC_STAT)` information about a segment
+fn segment_info(id: libc::c_int) -> Result<libc::shmid_ds, ShmemError> {
+    let mut info = MaybeUninit::<libc::shmid_ds>::uninit();
+    let res = unsafe { libc::shmctl(id, libc::IPC_STAT, info.as_mut_ptr()) };
+    trace!("shmctl({id}, IPC_STAT, {:p}) == {res}", info.as_ptr());
+    if res != 0 {
+        return Err(ShmemError::UnknownOsError(nix::Error::last() as u32));
+    }
+    Ok(unsafe { info.assume_init() })
+}
+
+/// Attaches the segment to our address space
+fn attach(map: &mut MapData, id: libc::c_int) -> Result<(), ShmemError> {
+    let ptr = unsafe { libc::shmat(id, null_mut(), 0) };
+    trace!("shmat({id}, NULL, 0) == {ptr:p}");
+    if ptr as isize == -1 {
+        return Err(ShmemError::MapOpenFailed(nix::Error::last() as u32));
+    }
+    map.map_ptr = ptr as *mut u8;
+    Ok(())
+}
+
+/// Creates a System V segment specified by its key and size
+pub fn create_mapping_sysv(
+    key: libc::key_t,
+    map_size: usize,
+    mode: Option<Mode>,
+    ext: &ShmemConfExt,
+    owner_election: bool,
+) -> Result<MapData, ShmemError> {
+    check_ext(ext, owner_election)?;
+    if map_size == 0 {
+        return Err(ShmemError::MapSizeZero);
+    }
+
+    debug!("Creating System V segment with key {key:#x}");
+    let mode = mode.unwrap_or(Mode::S_IRUSR | Mode::S_IWUSR);
+    let flags = libc::IPC_CREAT | libc::IPC_EXCL | mode.bits() as libc::c_int;
+    let id = unsafe { libc::shmget(key, map_size, flags) };
+    trace!("shmget({key:#x}, {map_size}, {flags:X}) == {id}");
+    if id == -1 {
+        return Err(match nix::Error::last() {
+            nix::Error::EEXIST => ShmemError::MappingIdExists,
+            e => ShmemError::MapCreateFailed(e as u32),
+        });
+    }
+
+    // Built right away so the segment gets removed if attaching fails
+    let mut new_map = new_map_data(key, id, map_size, true);
+    attach(&mut new_map, id).map_err(|e| match e {
+        ShmemError::MapOpenFailed(e) => ShmemError::MapCreateFailed(e),
+        e => e,
+    })?;
+    Ok(new_map)
+}
+
+/// Attaches to an existing System V segment specified by its key
+pub fn open_mapping_sysv(
+    key: libc::key_t,
+    ext: &ShmemConfExt,
+    owner_election: bool,
+) -> Result<MapData, ShmemError> {
+    check_ext(ext, owner_election)?;
+
+    debug!("Opening System V segment with key {key:#x}");
+    let id = unsafe { libc::shmget(key, 0, 0) };
+    trace!("shmget({key:#x}, 0, 0) == {id}");
+    if id == -1 {
+        return Err(ShmemError::MapOpenFailed(nix::Error::last() as u32));
+    }
+
+    let map_size = segment_info(id)?.shm_segsz as usize;
+    let mut new_map = new_map_data(key, id, map_size, false);
+    attach(&mut new_map, id)?;
+    Ok(new_map)
+}
+
+fn new_map_data(key: libc::key_t, id: libc::c_int, map_size: usize, owner: bool) -> MapData {
+    MapData {
+        owner,
+        map_fd: None,
+        sysv: Some(Segment { key, id }),
+        unique_id: format!("{key:#010x}"),
+        map_size,
+        map_ptr: null_mut(),
+        backend: None,
+        persistent: false,
+        private: false,
+        guard_len: 0,
+        mirrored: false,
+        liveness: None,
+    }
+}
@@ -0,0 +1,216 @@
+//! Waiting for a mapping to be created by another process
+
+#[cfg(target_os = "linux")]
+use std::ffi::CString;
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+#[cfg(target_os = "linux")]
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::log::*;
+use crate::{Shmem, ShmemConf, ShmemError};
+
+/// Longest time between two attempts, as some steps of `create()` (writing the header) do not
+/// generate filesystem events
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Whether `open()` failed because the mapping is not (fully) created yet
+fn is_not_ready(e: &ShmemError) -> bool {
+    match e {
+        ShmemError::MapOpenFailed(code) => *code == libc_enoent(),
+        ShmemError::LinkOpenFailed(e) => e.kind() == std::io::ErrorKind::NotFound,
+        // The object exists but was not sized or initialized yet
+        ShmemError::MapSizeZero | ShmemError::InvalidHeader => true,
+        _ => false,
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn libc_enoent() -> u32 {
+    libc::ENOENT as u32
+}
+
+#[cfg(target_os = "windows")]
+fn libc_enoent() -> u32 {
+    // ERROR_FILE_NOT_FOUND
+    2
+}
+
+impl ShmemConf {
+    /// Opens an existing mapping, waiting up to `timeout` for another process to create it
+    ///
+    /// On Linux, inotify is used on `/dev/shm`, the tmpfs directory or the directory of the flink to
+    /// retry as soon as the mapping is published. Fails with `Timeout` if the mapping does not
+    /// appear in time, or any other error that `open()` returns. A timeout too large to be represented,
+    /// such as `Duration::MAX`, waits forever.
+    pub fn open_wait(self, timeout: Duration) -> Result<Shmem, ShmemError> {
+        let deadline = Instant::now().checked_add(timeout);
+        let watcher = self.watch_dir().and_then(|dir| DirWatcher::new(&dir));
+
+        loop {
+            match self.clone().open() {
+                Err(e) if is_not_ready(&e) => debug!("Mapping is not ready yet : {e}"),
+                res => return res,
+            }
+
+            let wait = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(ShmemError::Timeout);
+                    }
+                    (deadline - now).min(POLL_INTERVAL)
+                }
+                None => POLL_INTERVAL,
+            };
+            match watcher {
+                Some(ref w) => w.wait(wait),
+                None => std::thread::sleep(wait),
+            }
+        }
+    }
+
+    /// Async version of `open_wait()`, which never blocks the runtime (`tokio` feature)
+    ///
+    /// A flink that is still being written is waited for like a mapping that does not exist yet,
+    /// instead of sleeping in `open()`.
+    #[cfg(feature = "tokio")]
+    pub async fn open_wait_async(self, timeout: Duration) -> Result<Shmem, ShmemError> {
+        let deadline = tokio::time::Instant::now().checked_add(timeout);
+        let from_flink = self.os_id.is_none() && self.flink_path.is_some();
+        #[cfg(target_os = "linux")]
+        let watcher = self
+            .watch_dir()
+            .and_then(|dir| DirWatcher::new(&dir))
+            // Safety: the watcher owns its descriptor until it is dropped
+            .and_then(|w| unsafe { tokio::io::unix::AsyncFd::register(w) }.ok());
+
+        loop {
+            match self.clone().open_with_flink_retries(false) {
+                Err(e) if is_not_ready(&e) => debug!("Mapping is not ready yet : {e}"),
+                Err(ShmemError::InvalidOsId) if from_flink => {
+                    debug!("The flink is not fully written yet")
+                }
+                res => return res,
+            }
+
+            let wait = match deadline {
+                Some(deadline) => {
+                    let now = tokio::time::Instant::now();
+                    if now >= deadline {
+                        return Err(ShmemError::Timeout);
+                    }
+                    (deadline - now).min(POLL_INTERVAL)
+                }
+                None => POLL_INTERVAL,
+            };
+            #[cfg(target_os = "linux")]
+            if let Some(ref w) = watcher {
+                if let Ok(Ok(mut guard)) = tokio::time::timeout(wait, w.readable()).await {
+                    w.get_ref().drain();
+                    guard.clear_ready();
+                }
+                continue;
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Returns the directory in which the mapping (or its flink) will appear
+    fn watch_dir(&self) -> Option<PathBuf> {
+        if let Some(ref flink_path) = self.flink_path {
+            return flink_path.parent().map(|v| match v.as_os_str().is_empty() {
+                true => PathBuf::from("."),
+                false => PathBuf::from(v),
+            });
+        }
+        if self.ext.sysv_key().is_some() {
+            return None;
+        }
+        if self.use_tmpfs {
+            return self.get_tmpfs_dir().ok();
+        }
+        if cfg!(target_os = "linux") {
+            // shm_open() objects live in /dev/shm on linux
+            return Some(PathBuf::from("/dev/shm"));
+        }
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Watches a directory for new or changed entries with inotify
+struct DirWatcher {
+    fd: OwnedFd,
+}
+
+#[cfg(target_os = "linux")]
+impl DirWatcher {
+    fn new(dir: &Path) -> Option<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd == -1 {
+            return None;
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        let mask = libc::IN_CREATE | libc::IN_MOVED_TO | libc::IN_CLOSE_WRITE | libc::IN_MODIFY;
+        let res = unsafe { libc::inotify_add_watch(fd.as_raw_fd(), path.as_ptr(), mask) };
+        trace!(
+            "inotify_add_watch({}, {:X}) == {}",
+            dir.display(),
+            mask,
+            res
+        );
+        if res == -1 {
+            // The directory might not exist yet, fall back to polling
+            return None;
+        }
+        Some(DirWatcher { fd })
+    }
+
+    /// Waits until something happens in the directory or `timeout` expires
+    fn wait(&self, timeout: Duration) {
+        let mut pfd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = timeout.as_millis().clamp(1, i32::MAX as u128) as i32;
+        if unsafe { libc::poll(&mut pfd, 1, timeout_ms) } > 0 {
+            self.drain();
+        }
+    }
+
+    /// Discards the pending events
+    fn drain(&self) {
+        let mut buf = [0u8; 4096];
+        while unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr() as *mut _, buf.len()) } > 0
+        {
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl AsRawFd for DirWatcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+/// inotify is only available on Linux, other platforms poll
+struct DirWatcher;
+
+#[cfg(not(target_os = "linux"))]
+impl DirWatcher {
+    fn new(_dir: &Path) -> Option<Self> {
+        None
+    }
+
+    fn wait(&self, timeout: Duration) {
+        std::thread::sleep(timeout)
+    }
+}
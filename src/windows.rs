@@ -21,6 +21,36 @@ impl ShmemConf {
     }
 }
 
+impl ShmemConf {
+    /// Creates and maps the object `unique_id`
+    pub(crate) fn create_object(&self, unique_id: &str) -> Result<MapData, ShmemError> {
+        create_mapping(unique_id, self.size, &self.ext)
+    }
+
+    /// Custom backends are not available on Windows
+    #[cfg(feature = "serde")]
+    pub(crate) fn has_custom_backend(&self) -> bool {
+        false
+    }
+
+    /// Maps the existing object `unique_id`
+    pub(crate) fn open_object(&self, unique_id: &str) -> Result<MapData, ShmemError> {
+        open_mapping(unique_id, self.size, &self.ext)
+    }
+}
+
+impl ShmemConfExt {
+    /// Whether os_ids are passed to the OS without validation
+    pub(crate) fn allows_raw_os_id(&self) -> bool {
+        self.allow_raw
+    }
+
+    /// System V segments are not available on Windows
+    pub(crate) fn sysv_key(&self) -> Option<i32> {
+        None
+    }
+}
+
 pub struct MapData {
     owner: bool,
 
@@ -111,6 +141,26 @@ impl MapData {
     pub fn as_mut_ptr(&self) -> *mut u8 {
         self.view.as_mut_ptr() as _
     }
+    pub fn set_persistent(&mut self, _persistent: bool) {
+        // Persistent mode is only available with tmpfs which is not supported on Windows
+    }
+    pub fn try_lock_liveness(&self) -> Result<bool, ShmemError> {
+        // Owner election is not supported on Windows
+        Ok(true)
+    }
+    pub fn unlock_liveness(&self) {}
+    pub fn lock_liveness(&self) -> Result<(), ShmemError> {
+        Err(ShmemError::Unsupported)
+    }
+    pub fn open_liveness(&mut self) -> Result<(), ShmemError> {
+        Err(ShmemError::Unsupported)
+    }
+    pub fn attach(&self) -> Result<(), ShmemError> {
+        Err(ShmemError::Unsupported)
+    }
+    pub fn detach(&self) -> Result<bool, ShmemError> {
+        Err(ShmemError::Unsupported)
+    }
 }
 
 /// Returns the path to a temporary directory in which to store files backing the shared memory. If it
@@ -267,7 +317,11 @@ fn new_map(
 }
 
 //Creates a mapping specified by the uid and size
-pub fn create_mapping(unique_id: &str, map_size: usize) -> Result<MapData, ShmemError> {
+pub fn create_mapping(
+    unique_id: &str,
+    map_size: usize,
+    _ext: &ShmemConfExt,
+) -> Result<MapData, ShmemError> {
     new_map(unique_id, map_size, true, false)
 }
 
@@ -280,10 +334,19 @@ pub fn open_mapping(
     new_map(unique_id, map_size, false, ext.allow_raw)
 }
 
-pub fn create_mapping_tmpfs(_file_path: &str, _map_size: usize) -> Result<MapData, ShmemError> {
-    unimplemented!()
+pub fn create_mapping_sysv(
+    _key: i32,
+    _map_size: usize,
+    _ext: &ShmemConfExt,
+    _owner_election: bool,
+) -> Result<MapData, ShmemError> {
+    Err(ShmemError::Unsupported)
 }
 
-pub fn open_mapping_tmpfs(_file_path: &str, _expected_size: usize) -> Result<MapData, ShmemError> {
-    unimplemented!()
+pub fn open_mapping_sysv(
+    _key: i32,
+    _ext: &ShmemConfExt,
+    _owner_election: bool,
+) -> Result<MapData, ShmemError> {
+    Err(ShmemError::Unsupported)
 }
@@ -0,0 +1,101 @@
+use std::sync::atomic::Ordering;
+
+use shared_memory::{ShmemConf, ShmemError};
+
+#[test]
+fn read_write_at() {
+    let mut s1 = ShmemConf::new().size(4096).create().unwrap();
+    let s2 = ShmemConf::new().os_id(s1.get_os_id()).open().unwrap();
+
+    s1.write_at(8, 0xDEAD_BEEF_u32).unwrap();
+    s1.write_at(16, [1.5f64, -2.0]).unwrap();
+    assert_eq!(s2.read_at::<u32>(8).unwrap(), 0xDEAD_BEEF);
+    assert_eq!(s2.read_at::<[f64; 2]>(16).unwrap(), [1.5, -2.0]);
+    assert_eq!(s2.read_at::<u8>(4095).unwrap(), 0);
+
+    assert!(matches!(
+        s1.read_at::<u32>(4094),
+        Err(ShmemError::InvalidRange)
+    ));
+    assert!(matches!(
+        s1.write_at(usize::MAX, 0u8),
+        Err(ShmemError::InvalidRange)
+    ));
+    assert!(matches!(
+        s1.read_at::<u32>(2),
+        Err(ShmemError::UnalignedOffset)
+    ));
+}
+
+#[test]
+fn copy_from_to() {
+    let mut s = ShmemConf::new().size(64).create().unwrap();
+    s.copy_from(60, b"abcd").unwrap();
+
+    let mut buf = [0u8; 6];
+    s.copy_to(58, &mut buf).unwrap();
+    assert_eq!(&buf, b"\0\0abcd");
+
+    assert!(matches!(
+        s.copy_from(61, b"abcd"),
+        Err(ShmemError::InvalidRange)
+    ));
+    assert!(matches!(
+        s.copy_to(64, &mut buf),
+        Err(ShmemError::InvalidRange)
+    ));
+    s.copy_to(64, &mut []).unwrap();
+}
+
+#[test]
+fn atomics_at() {
+    let s1 = ShmemConf::new().size(4096).create().unwrap();
+    let s2 = ShmemConf::new().os_id(s1.get_os_id()).open().unwrap();
+
+    let a1 = s1.atomic_u64_at(64).unwrap();
+    let a2 = s2.atomic_u64_at(64).unwrap();
+    a1.store(5, Ordering::SeqCst);
+    assert_eq!(a2.fetch_add(1, Ordering::SeqCst), 5);
+    assert_eq!(a1.load(Ordering::SeqCst), 6);
+
+    s1.atomic_u32_at(4092).unwrap().store(7, Ordering::Relaxed);
+    assert_eq!(s2.read_at::<u32>(4092).unwrap(), 7);
+
+    assert!(matches!(
+        s1.atomic_u32_at(6),
+        Err(ShmemError::UnalignedOffset)
+    ));
+    assert!(matches!(
+        s1.atomic_u64_at(4096),
+        Err(ShmemError::InvalidRange)
+    ));
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn protected_ranges() {
+    use shared_memory::Protection;
+
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+    let mut s = ShmemConf::new().size(2 * page).create().unwrap();
+    s.protect(0..page, Protection::ReadOnly).unwrap();
+    s.protect(page..2 * page, Protection::None).unwrap();
+
+    assert_eq!(s.read_at::<u32>(0).unwrap(), 0);
+    assert!(matches!(
+        s.write_at(0, 1u32),
+        Err(ShmemError::RangeProtected)
+    ));
+    assert!(matches!(
+        s.atomic_u32_at(0),
+        Err(ShmemError::RangeProtected)
+    ));
+    assert!(matches!(
+        s.read_at::<u32>(page),
+        Err(ShmemError::RangeProtected)
+    ));
+    assert!(matches!(
+        s.copy_to(page - 2, &mut [0u8; 4]),
+        Err(ShmemError::RangeProtected)
+    ));
+}
@@ -0,0 +1,102 @@
+#![cfg(not(target_os = "windows"))]
+
+use shared_memory::{Advice, ShmemConf, ShmemError};
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+#[test]
+fn advise_access_patterns() {
+    let s = ShmemConf::new().size(4 * page_size()).create().unwrap();
+
+    for advice in [Advice::Sequential, Advice::Random, Advice::WillNeed] {
+        s.advise(0..s.len(), advice).unwrap();
+    }
+}
+
+#[test]
+fn advise_dont_need_keeps_contents() {
+    let page = page_size();
+    let s1 = ShmemConf::new().size(2 * page).create().unwrap();
+    let s2 = ShmemConf::new().os_id(s1.get_os_id()).open().unwrap();
+    unsafe { s1.as_ptr().write_volatile(0xAB) };
+
+    s1.advise(0..page, Advice::DontNeed).unwrap();
+    assert_eq!(unsafe { s1.as_ptr().read_volatile() }, 0xAB);
+    assert_eq!(unsafe { s2.as_ptr().read_volatile() }, 0xAB);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn advise_remove_punches_hole() {
+    let page = page_size();
+    let s1 = ShmemConf::new().size(2 * page).create().unwrap();
+    let s2 = ShmemConf::new().os_id(s1.get_os_id()).open().unwrap();
+    unsafe {
+        s1.as_ptr().write_volatile(0xAB);
+        s1.as_ptr().add(page).write_volatile(0xCD);
+    }
+
+    s1.advise(0..page, Advice::Remove).unwrap();
+
+    // Every process sees the removed page as zeros, the rest is untouched
+    assert_eq!(unsafe { s2.as_ptr().read_volatile() }, 0);
+    assert_eq!(unsafe { s1.as_ptr().read_volatile() }, 0);
+    assert_eq!(unsafe { s2.as_ptr().add(page).read_volatile() }, 0xCD);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn advise_linux_only() {
+    let s = ShmemConf::new().size(page_size()).create().unwrap();
+
+    s.advise(0..s.len(), Advice::DontDump).unwrap();
+    s.advise(0..s.len(), Advice::DontFork).unwrap();
+    // Transparent huge pages may be disabled on this machine
+    match s.advise(0..s.len(), Advice::HugePage) {
+        Ok(_) | Err(ShmemError::AdviseFailed(_)) => {}
+        Err(e) => panic!("unexpected error {}", e),
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn advise_mirrored_both_views() {
+    let s = ShmemConf::new()
+        .size(page_size())
+        .mirrored(true)
+        .create()
+        .unwrap();
+    let mirror = unsafe { s.as_ptr().add(s.len()) };
+    s.advise(0..s.len(), Advice::DontFork).unwrap();
+
+    // Neither view exists in a forked child
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        unsafe {
+            mirror.read_volatile();
+            libc::_exit(0)
+        };
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFSIGNALED(status));
+    assert_eq!(libc::WTERMSIG(status), libc::SIGSEGV);
+}
+
+#[test]
+fn advise_validation() {
+    let page = page_size();
+    let s = ShmemConf::new().size(2 * page).create().unwrap();
+
+    assert!(matches!(
+        s.advise(1..page, Advice::DontNeed),
+        Err(ShmemError::UnalignedRange)
+    ));
+    assert!(matches!(
+        s.advise(0..3 * page, Advice::DontNeed),
+        Err(ShmemError::InvalidRange)
+    ));
+}
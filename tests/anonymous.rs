@@ -0,0 +1,103 @@
+#![cfg(not(target_os = "windows"))]
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+use shared_memory::{ShmemConf, ShmemError};
+
+/// Forks, running `f` in the child which exits with the code it returns
+fn fork(f: impl FnOnce() -> i32) -> libc::pid_t {
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        let code = f();
+        unsafe { libc::_exit(code) };
+    }
+    pid
+}
+
+/// Waits for the child `pid` and returns its exit code
+fn wait(pid: libc::pid_t) -> i32 {
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFEXITED(status));
+    libc::WEXITSTATUS(status)
+}
+
+/// Waits for `flag` to hold `value`, returns false on timeout
+fn wait_for(flag: &AtomicU8, value: u8) -> bool {
+    let start = Instant::now();
+    while flag.load(Ordering::Acquire) != value {
+        if start.elapsed() > Duration::from_secs(10) {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    true
+}
+
+#[test]
+fn anonymous_shared_with_children() {
+    let mut s = ShmemConf::new().size(4096).anonymous().unwrap();
+    assert_eq!(s.len(), 4096);
+    assert_eq!(s.get_os_id(), "");
+    unsafe { s.as_slice_mut()[1] = 0xAB };
+
+    let flag = unsafe { &*(s.as_ptr() as *const AtomicU8) };
+    let ptr = s.as_ptr();
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        // The child sees writes made before and after the fork
+        let code = if unsafe { ptr.add(1).read_volatile() } != 0xAB || !wait_for(flag, 1) {
+            1
+        } else {
+            unsafe { ptr.add(2).write_volatile(0xCD) };
+            flag.store(2, Ordering::Release);
+            // Unmaps the child's view only
+            drop(s);
+            0
+        };
+        unsafe { libc::_exit(code) };
+    }
+
+    flag.store(1, Ordering::Release);
+    assert_eq!(wait(pid), 0);
+    assert_eq!(flag.load(Ordering::Acquire), 2);
+    assert_eq!(unsafe { ptr.add(2).read_volatile() }, 0xCD);
+}
+
+#[test]
+fn anonymous_many_children() {
+    let s = ShmemConf::new()
+        .size(4096)
+        .guard_pages(1)
+        .anonymous()
+        .unwrap();
+    let ptr = s.as_ptr();
+
+    let pids: Vec<_> = (0..4)
+        .map(|i| {
+            fork(move || {
+                unsafe { ptr.add(i).write_volatile(i as u8 + 1) };
+                0
+            })
+        })
+        .collect();
+    for pid in pids {
+        assert_eq!(wait(pid), 0);
+    }
+    assert_eq!(unsafe { s.as_slice() }[..4], [1, 2, 3, 4]);
+}
+
+#[test]
+fn anonymous_validation() {
+    assert!(matches!(
+        ShmemConf::new().anonymous(),
+        Err(ShmemError::MapSizeZero)
+    ));
+    assert!(matches!(
+        ShmemConf::new().size(4096).owner_election(true).anonymous(),
+        Err(ShmemError::Unsupported)
+    ));
+}
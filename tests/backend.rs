@@ -0,0 +1,105 @@
+#![cfg(not(target_os = "windows"))]
+
+use std::collections::HashMap;
+use std::os::fd::OwnedFd;
+use std::sync::{Arc, Mutex};
+
+use shared_memory::{
+    Mode, PosixShmBackend, ShmemBackend, ShmemConf, ShmemError, ShmemInfo, TmpfsBackend,
+};
+
+/// Keeps its objects in memory as unlinked tmpfs files
+#[derive(Clone, Default)]
+struct TestBackend {
+    objects: Arc<Mutex<HashMap<String, OwnedFd>>>,
+}
+
+impl ShmemBackend for TestBackend {
+    fn create(&self, unique_id: &str, size: usize, mode: Mode) -> Result<OwnedFd, ShmemError> {
+        let mut objects = self.objects.lock().unwrap();
+        if objects.contains_key(unique_id) {
+            return Err(ShmemError::MappingIdExists);
+        }
+        let path = std::env::temp_dir().join(format!("backend_{}", std::process::id()));
+        let fd = TmpfsBackend.create(path.to_str().unwrap(), size, mode)?;
+        TmpfsBackend.unlink(path.to_str().unwrap())?;
+        objects.insert(String::from(unique_id), fd.try_clone().unwrap());
+        Ok(fd)
+    }
+
+    fn open(&self, unique_id: &str) -> Result<OwnedFd, ShmemError> {
+        match self.objects.lock().unwrap().get(unique_id) {
+            Some(fd) => Ok(fd.try_clone().unwrap()),
+            None => Err(ShmemError::MapOpenFailed(libc::ENOENT as u32)),
+        }
+    }
+
+    fn unlink(&self, unique_id: &str) -> Result<(), ShmemError> {
+        self.objects.lock().unwrap().remove(unique_id);
+        Ok(())
+    }
+}
+
+#[test]
+fn custom_backend() {
+    let backend = TestBackend::default();
+    let s1 = ShmemConf::new()
+        .size(4096)
+        .os_id("custom")
+        .backend(backend.clone())
+        .create()
+        .unwrap();
+    assert!(backend.objects.lock().unwrap().contains_key("/custom"));
+    assert!(matches!(
+        ShmemConf::new()
+            .size(4096)
+            .os_id("custom")
+            .backend(backend.clone())
+            .create(),
+        Err(ShmemError::MappingIdExists)
+    ));
+    // The object does not exist outside of the backend
+    assert!(ShmemConf::new().os_id("custom").open().is_err());
+
+    let s2 = ShmemConf::new()
+        .os_id("custom")
+        .backend(backend.clone())
+        .open()
+        .unwrap();
+    unsafe { s1.as_ptr().write_volatile(0xAB) };
+    assert_eq!(unsafe { s2.as_ptr().read_volatile() }, 0xAB);
+
+    assert_eq!(backend.info("/custom").unwrap().size, 4096);
+
+    // The owner unlinks through the backend
+    drop(s2);
+    drop(s1);
+    assert!(backend.objects.lock().unwrap().is_empty());
+}
+
+#[test]
+fn builtin_backends() {
+    let s = ShmemConf::new()
+        .size(4096)
+        .mode(Mode::S_IRUSR | Mode::S_IWUSR)
+        .create()
+        .unwrap();
+    assert_eq!(
+        PosixShmBackend.info(s.get_os_id()).unwrap(),
+        ShmemInfo {
+            size: 4096,
+            mode: Mode::S_IRUSR | Mode::S_IWUSR,
+        }
+    );
+
+    let s = ShmemConf::new()
+        .size(4096)
+        .use_tmpfs_with_dir("/tmp")
+        .create()
+        .unwrap();
+    assert_eq!(TmpfsBackend.info(s.get_os_id()).unwrap().size, 4096);
+    let os_id = String::from(s.get_os_id());
+    drop(s);
+    assert!(TmpfsBackend.info(&os_id).is_err());
+    TmpfsBackend.unlink(&os_id).unwrap();
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "bytes")]
+
+use bytes::BufMut;
+use shared_memory::{ShmemConf, ShmemError};
+
+#[test]
+fn to_bytes_keeps_mapping_alive() {
+    let mut s = ShmemConf::new().size(64).create().unwrap();
+    s.copy_from(8, b"payload").unwrap();
+    let os_id = s.get_os_id().to_string();
+    let handle = s.into_handle();
+
+    let bytes = unsafe { handle.to_bytes(8..15) }.unwrap();
+    assert_eq!(&bytes[..], b"payload");
+    // No copy was made
+    assert_eq!(bytes.as_ptr(), unsafe { handle.as_ptr().add(8) }
+        as *const u8);
+
+    let tail = bytes.slice(3..);
+    drop(bytes);
+    drop(handle);
+    assert_eq!(&tail[..], b"load");
+    assert!(ShmemConf::new().os_id(&os_id).open().is_ok());
+
+    // The last reference unmaps and deletes the mapping
+    drop(tail);
+    assert!(ShmemConf::new().os_id(&os_id).open().is_err());
+}
+
+#[test]
+fn to_bytes_invalid_range() {
+    let handle = ShmemConf::new().size(64).create().unwrap().into_handle();
+    assert!(matches!(
+        unsafe { handle.to_bytes(60..65) },
+        Err(ShmemError::InvalidRange)
+    ));
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = 10..5;
+    assert!(matches!(
+        unsafe { handle.to_bytes(reversed) },
+        Err(ShmemError::InvalidRange)
+    ));
+    assert!(unsafe { handle.to_bytes(64..64) }.unwrap().is_empty());
+}
+
+#[test]
+fn buf_mut_writes_in_place() {
+    let mut s = ShmemConf::new().size(64).create().unwrap();
+
+    let mut w = s.buf_mut(16..32).unwrap();
+    w.put_u32(0xAABBCCDD);
+    w.put_slice(b"abc");
+    assert_eq!(w.written(), 7);
+    assert_eq!(w.remaining_mut(), 9);
+
+    let mut buf = [0u8; 8];
+    s.copy_to(16, &mut buf).unwrap();
+    assert_eq!(&buf, b"\xAA\xBB\xCC\xDDabc\0");
+
+    assert!(matches!(s.buf_mut(60..68), Err(ShmemError::InvalidRange)));
+}
+
+#[test]
+#[should_panic]
+fn buf_mut_overflow_panics() {
+    let mut s = ShmemConf::new().size(64).create().unwrap();
+    let mut w = s.buf_mut(0..4).unwrap();
+    w.put_u64(1);
+}
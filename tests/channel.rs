@@ -0,0 +1,303 @@
+#![cfg(all(feature = "channel", not(target_os = "windows")))]
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use shared_memory::{channel, ShmemError};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Msg {
+    id: u32,
+    name: String,
+}
+
+fn msg(id: u32) -> Msg {
+    Msg {
+        id,
+        name: format!("message {id}"),
+    }
+}
+
+#[test]
+fn send_recv_between_threads() {
+    let mut rx = channel::<Msg>().capacity(256).create_receiver().unwrap();
+    let mut tx = channel::<Msg>()
+        .os_id(rx.get_os_id())
+        .open_sender()
+        .unwrap();
+
+    // The ring is much smaller than everything sent, so the sender waits and wraps around
+    let sender = std::thread::spawn(move || {
+        for id in 0..1000 {
+            tx.send(&msg(id)).unwrap();
+        }
+    });
+    for id in 0..1000 {
+        assert_eq!(rx.recv().unwrap(), msg(id));
+    }
+    sender.join().unwrap();
+
+    // Every message was received before the disconnect is reported
+    assert!(matches!(rx.recv(), Err(ShmemError::Disconnected)));
+}
+
+#[test]
+fn non_blocking() {
+    let mut tx = channel::<u64>().capacity(32).create_sender().unwrap();
+    let mut rx = channel::<u64>()
+        .os_id(tx.get_os_id())
+        .open_receiver()
+        .unwrap();
+
+    assert!(matches!(rx.try_recv(), Err(ShmemError::ChannelEmpty)));
+    assert!(matches!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(ShmemError::Timeout)
+    ));
+
+    // Backpressure once the ring is full
+    let mut sent = 0;
+    loop {
+        match tx.try_send(&u64::MAX) {
+            Ok(_) => sent += 1,
+            Err(ShmemError::ChannelFull) => break,
+            Err(e) => panic!("unexpected error {}", e),
+        }
+    }
+    assert!(sent > 0);
+    assert!(matches!(
+        tx.send_timeout(&u64::MAX, Duration::from_millis(10)),
+        Err(ShmemError::Timeout)
+    ));
+
+    assert_eq!(rx.try_recv().unwrap(), u64::MAX);
+    tx.try_send(&1).unwrap();
+    for _ in 1..sent {
+        assert_eq!(rx.try_recv().unwrap(), u64::MAX);
+    }
+    assert_eq!(rx.try_recv().unwrap(), 1);
+}
+
+#[test]
+fn message_too_large() {
+    let mut tx = channel::<String>().capacity(16).create_sender().unwrap();
+    assert!(matches!(
+        tx.send(&"x".repeat(100)),
+        Err(ShmemError::MessageTooLarge(102))
+    ));
+}
+
+#[test]
+fn receiver_dropped() {
+    let mut tx = channel::<u32>().create_sender().unwrap();
+    // Sending before the receiver connects is fine
+    tx.send(&1).unwrap();
+    assert!(!tx.is_disconnected());
+
+    let rx = channel::<u32>()
+        .os_id(tx.get_os_id())
+        .open_receiver()
+        .unwrap();
+    drop(rx);
+    assert!(tx.is_disconnected());
+    assert!(matches!(tx.send(&2), Err(ShmemError::Disconnected)));
+
+    // Another receiver can take over
+    let mut rx = channel::<u32>()
+        .os_id(tx.get_os_id())
+        .open_receiver()
+        .unwrap();
+    assert_eq!(rx.recv().unwrap(), 1);
+    tx.send(&3).unwrap();
+    assert_eq!(rx.recv().unwrap(), 3);
+}
+
+#[test]
+fn half_in_use() {
+    let tx = channel::<u32>().create_sender().unwrap();
+    let _rx = channel::<u32>()
+        .os_id(tx.get_os_id())
+        .open_receiver()
+        .unwrap();
+    assert!(matches!(
+        channel::<u32>().os_id(tx.get_os_id()).open_receiver(),
+        Err(ShmemError::ChannelInUse)
+    ));
+    assert!(matches!(
+        channel::<u32>().os_id(tx.get_os_id()).open_sender(),
+        Err(ShmemError::ChannelInUse)
+    ));
+}
+
+#[test]
+fn sender_process_exits() {
+    let mut rx = channel::<u32>().create_receiver().unwrap();
+    let os_id = rx.get_os_id().to_string();
+
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        // Exit without dropping the sender, as a crash would
+        let code = match channel::<u32>().os_id(&os_id).open_sender() {
+            Ok(mut tx) => match tx.send(&42) {
+                Ok(_) => 0,
+                Err(_) => 2,
+            },
+            Err(_) => 1,
+        };
+        unsafe { libc::_exit(code) };
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert_eq!(libc::WEXITSTATUS(status), 0);
+
+    assert_eq!(rx.recv().unwrap(), 42);
+    assert!(rx.is_disconnected());
+    assert!(matches!(rx.recv(), Err(ShmemError::Disconnected)));
+
+    // The half of the dead process can be opened again
+    let mut tx = channel::<u32>().os_id(&os_id).open_sender().unwrap();
+    tx.send(&7).unwrap();
+    assert_eq!(rx.recv().unwrap(), 7);
+}
+
+#[test]
+fn sender_exits_with_forked_child() {
+    let mut rx = channel::<u32>().create_receiver().unwrap();
+    let os_id = rx.get_os_id().to_string();
+    let mut ready = [0; 2];
+    let mut hold = [0; 2];
+    assert_eq!(unsafe { libc::pipe(ready.as_mut_ptr()) }, 0);
+    assert_eq!(unsafe { libc::pipe(hold.as_mut_ptr()) }, 0);
+
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        let mut tx = match channel::<u32>().os_id(&os_id).open_sender() {
+            Ok(v) => v,
+            Err(_) => unsafe { libc::_exit(1) },
+        };
+        if tx.send(&42).is_err() {
+            unsafe { libc::_exit(2) };
+        }
+        // The child inherits the sender and outlives us
+        if unsafe { libc::fork() } == 0 {
+            let mut b = 0u8;
+            unsafe {
+                libc::close(hold[1]);
+                libc::write(ready[1], &b as *const u8 as _, 1);
+                libc::read(hold[0], &mut b as *mut u8 as _, 1);
+                libc::_exit(0);
+            }
+        }
+        let mut b = 0u8;
+        unsafe {
+            libc::read(ready[0], &mut b as *mut u8 as _, 1);
+            libc::_exit(0);
+        }
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert_eq!(libc::WEXITSTATUS(status), 0);
+
+    assert_eq!(rx.recv().unwrap(), 42);
+    assert!(rx.is_disconnected());
+    unsafe { libc::close(hold[1]) };
+}
+
+#[test]
+fn timeout_overflow() {
+    let mut tx = channel::<u32>().create_sender().unwrap();
+    let mut rx = channel::<u32>()
+        .os_id(tx.get_os_id())
+        .open_receiver()
+        .unwrap();
+
+    // A timeout too large for the clock waits forever
+    tx.send_timeout(&1, Duration::MAX).unwrap();
+    let sender = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(10));
+        tx.send(&2).unwrap();
+    });
+    assert_eq!(rx.recv_timeout(Duration::MAX).unwrap(), 1);
+    assert_eq!(rx.recv_timeout(Duration::MAX).unwrap(), 2);
+    sender.join().unwrap();
+}
+
+#[test]
+fn capacity_overflow() {
+    assert!(matches!(
+        channel::<u32>().capacity(usize::MAX).create_sender(),
+        Err(ShmemError::InvalidRange)
+    ));
+}
+
+#[test]
+fn sysv_unsupported() {
+    let key = ((std::process::id() as i32) << 8 | 0x7f) as libc::key_t;
+    assert!(matches!(
+        channel::<u32>()
+            .shmem_conf(shared_memory::ShmemConf::new().sysv_key(key))
+            .create_sender(),
+        Err(ShmemError::Unsupported)
+    ));
+}
+
+#[test]
+fn decode_failure_skips_message() {
+    let mut tx = channel::<serde_json::Value>().create_sender().unwrap();
+    let mut rx = channel::<u32>()
+        .os_id(tx.get_os_id())
+        .open_receiver()
+        .unwrap();
+
+    tx.send(&serde_json::json!("not a number")).unwrap();
+    tx.send(&serde_json::json!(5)).unwrap();
+    assert!(matches!(rx.recv(), Err(ShmemError::CodecFailed(_))));
+    assert_eq!(rx.recv().unwrap(), 5);
+}
+
+#[test]
+fn open_uninitialized() {
+    let s = shared_memory::ShmemConf::new().size(4096).create().unwrap();
+    assert!(matches!(
+        channel::<u32>().os_id(s.get_os_id()).open_receiver(),
+        Err(ShmemError::InvalidHeader)
+    ));
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn bincode_codec() {
+    use shared_memory::BincodeCodec;
+
+    let mut tx = channel::<Msg>()
+        .codec(BincodeCodec)
+        .create_sender()
+        .unwrap();
+    let mut rx = channel::<Msg>()
+        .codec(BincodeCodec)
+        .os_id(tx.get_os_id())
+        .open_receiver()
+        .unwrap();
+    tx.send(&msg(1)).unwrap();
+    assert_eq!(rx.recv().unwrap(), msg(1));
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn postcard_codec() {
+    use shared_memory::PostcardCodec;
+
+    let mut tx = channel::<Msg>()
+        .codec(PostcardCodec)
+        .create_sender()
+        .unwrap();
+    let mut rx = channel::<Msg>()
+        .codec(PostcardCodec)
+        .os_id(tx.get_os_id())
+        .open_receiver()
+        .unwrap();
+    tx.send(&msg(2)).unwrap();
+    assert_eq!(rx.recv().unwrap(), msg(2));
+}
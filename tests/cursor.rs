@@ -0,0 +1,89 @@
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+
+use shared_memory::ShmemConf;
+
+#[test]
+fn cursor_read_write_seek() {
+    let mut s = ShmemConf::new().size(16).create().unwrap();
+    let mut c = s.cursor();
+
+    c.write_all(b"hello").unwrap();
+    assert_eq!(c.position(), 5);
+    assert_eq!(c.seek(SeekFrom::Current(-5)).unwrap(), 0);
+
+    let mut buf = [0u8; 5];
+    c.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+
+    assert_eq!(c.seek(SeekFrom::End(-1)).unwrap(), 15);
+    let mut rest = Vec::new();
+    assert_eq!(c.read_to_end(&mut rest).unwrap(), 1);
+    assert_eq!(c.read(&mut buf).unwrap(), 0);
+
+    assert!(c.seek(SeekFrom::Current(-17)).is_err());
+    assert_eq!(c.position(), 16);
+}
+
+#[test]
+fn cursor_write_past_end() {
+    let mut s = ShmemConf::new().size(8).create().unwrap();
+    let mut c = s.cursor();
+    c.seek(SeekFrom::Start(6)).unwrap();
+
+    let e = c.write_all(b"abcd").unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::WriteZero);
+    // What fits was written
+    let mut buf = [0u8; 2];
+    s.copy_to(6, &mut buf).unwrap();
+    assert_eq!(&buf, b"ab");
+}
+
+#[test]
+fn cursor_buf_read() {
+    let mut s = ShmemConf::new().size(32).create().unwrap();
+    s.copy_from(0, b"first\nsecond\n").unwrap();
+
+    let mut c = s.cursor();
+    let mut line = String::new();
+    c.read_line(&mut line).unwrap();
+    assert_eq!(line, "first\n");
+    assert_eq!(c.position(), 6);
+
+    // Writes are visible to the following reads
+    c.write_all(b"SE").unwrap();
+    line.clear();
+    c.seek(SeekFrom::Start(6)).unwrap();
+    c.read_line(&mut line).unwrap();
+    assert_eq!(line, "SEcond\n");
+}
+
+#[test]
+fn cursor_copy_files() {
+    let data: Vec<u8> = (0..20_000u32).map(|v| v as u8).collect();
+    let dir = std::env::temp_dir();
+    let src_path = dir.join(format!("shmem_cursor_src_{}", std::process::id()));
+    let dst_path = dir.join(format!("shmem_cursor_dst_{}", std::process::id()));
+    std::fs::write(&src_path, &data).unwrap();
+
+    let mut s = ShmemConf::new().size(data.len()).create().unwrap();
+    let mut src = std::fs::File::open(&src_path).unwrap();
+    assert_eq!(
+        std::io::copy(&mut src, &mut s.cursor()).unwrap(),
+        data.len() as u64
+    );
+
+    let mut dst = std::fs::File::create(&dst_path).unwrap();
+    assert_eq!(
+        std::io::copy(&mut s.cursor(), &mut dst).unwrap(),
+        data.len() as u64
+    );
+    assert_eq!(std::fs::read(&dst_path).unwrap(), data);
+
+    // The file does not fit in a smaller mapping
+    let mut small = ShmemConf::new().size(100).create().unwrap();
+    let mut src = std::fs::File::open(&src_path).unwrap();
+    assert!(std::io::copy(&mut src, &mut small.cursor()).is_err());
+
+    std::fs::remove_file(src_path).unwrap();
+    std::fs::remove_file(dst_path).unwrap();
+}
@@ -0,0 +1,100 @@
+#![cfg(all(feature = "serde", not(target_os = "windows")))]
+
+use std::convert::TryFrom;
+
+use shared_memory::{DescriptorBackend, ShmemConf, ShmemDescriptor, ShmemError};
+
+/// Sends the descriptor through JSON and opens the mapping it describes
+fn reopen(desc: &ShmemDescriptor) -> shared_memory::Shmem {
+    let json = serde_json::to_string(desc).unwrap();
+    let desc: ShmemDescriptor = serde_json::from_str(&json).unwrap();
+    ShmemConf::try_from(desc).unwrap().open().unwrap()
+}
+
+#[test]
+fn descriptor_shm() {
+    let s1 = ShmemConf::new()
+        .size(4096)
+        .shared_ownership(true)
+        .create()
+        .unwrap();
+    let desc = s1.descriptor().unwrap();
+    assert_eq!(desc.os_id, s1.get_os_id());
+    assert_eq!(desc.size, 4096);
+    assert_eq!(desc.backend, DescriptorBackend::Shm);
+    assert!(desc.shared_ownership);
+
+    let s2 = reopen(&desc);
+    assert_eq!(s2.len(), 4096);
+    unsafe { s1.as_ptr().write_volatile(0xAB) };
+    assert_eq!(unsafe { s2.as_ptr().read_volatile() }, 0xAB);
+    assert_eq!(s2.descriptor().unwrap().os_id, desc.os_id);
+}
+
+#[test]
+fn descriptor_tmpfs() {
+    let s1 = ShmemConf::new()
+        .size(4096)
+        .use_tmpfs_with_dir("/tmp")
+        .namespace(format!("descriptor_{}", std::process::id()))
+        .create()
+        .unwrap();
+    let desc = s1.descriptor().unwrap();
+    assert_eq!(desc.backend, DescriptorBackend::Tmpfs);
+    assert_eq!(
+        desc.tmpfs_dir.as_ref().unwrap().join(&desc.os_id),
+        s1.get_tmpfs_file_path().unwrap()
+    );
+
+    let s2 = reopen(&desc);
+    assert_eq!(s2.get_os_id(), s1.get_os_id());
+}
+
+#[test]
+fn descriptor_persistent() {
+    let dir = std::env::temp_dir().join(format!("descriptor_persistent_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut s1 = ShmemConf::new()
+        .size(4096)
+        .persistent_with_dir(&dir)
+        .create()
+        .unwrap();
+    let desc = s1.descriptor().unwrap();
+    assert_eq!(desc.backend, DescriptorBackend::Tmpfs);
+    assert!(desc.persistent);
+
+    // Whoever ends up owning the reopened mapping keeps the file
+    let mut s2 = reopen(&desc);
+    s1.set_owner(false);
+    s2.set_owner(true);
+    let path = s2.get_tmpfs_file_path().unwrap();
+    drop((s1, s2));
+    assert!(path.is_file());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn descriptor_sysv() {
+    let key = (std::process::id() as i32) << 8 | 0x42;
+    let s1 = ShmemConf::new().size(4096).sysv_key(key).create().unwrap();
+    let desc = s1.descriptor().unwrap();
+    assert_eq!(desc.backend, DescriptorBackend::SysV { key: key.into() });
+
+    let s2 = reopen(&desc);
+    assert_eq!(s1.attach_count().unwrap(), 2);
+    drop(s2);
+}
+
+#[test]
+fn descriptor_json() {
+    let desc: ShmemDescriptor =
+        serde_json::from_str(r#"{"os_id":"/data","size":64,"backend":"shm","mode":384}"#).unwrap();
+    assert_eq!(desc.tmpfs_dir, None);
+    assert_eq!(desc.mode, Some(0o600));
+    assert!(!desc.shared_ownership);
+    assert!(!desc.persistent);
+
+    let s = ShmemConf::new().size(4096).anonymous().unwrap();
+    assert!(matches!(s.descriptor(), Err(ShmemError::Unsupported)));
+}
@@ -0,0 +1,98 @@
+#![cfg(not(target_os = "windows"))]
+
+use shared_memory::{ShmemConf, ShmemError};
+
+/// Runs `f` in a forked child and returns the signal that killed it, if any
+fn signal_in_child(f: impl FnOnce()) -> Option<i32> {
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        f();
+        unsafe { libc::_exit(0) };
+    }
+
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    if libc::WIFSIGNALED(status) {
+        Some(libc::WTERMSIG(status))
+    } else {
+        None
+    }
+}
+
+#[test]
+fn guard_pages_fault() {
+    let s1 = ShmemConf::new().size(4096).guard_pages(1).create().unwrap();
+    let s2 = ShmemConf::new()
+        .os_id(s1.get_os_id())
+        .guard_pages(2)
+        .open()
+        .unwrap();
+
+    for s in [&s1, &s2] {
+        let ptr = s.as_ptr();
+        let len = s.len();
+
+        // The mapping itself is usable
+        assert_eq!(
+            signal_in_child(|| unsafe {
+                ptr.write_volatile(1);
+                ptr.add(len - 1).write_volatile(1);
+            }),
+            None
+        );
+        // Just before and just after it isn't
+        assert_eq!(
+            signal_in_child(|| unsafe { ptr.sub(1).write_volatile(1) }),
+            Some(libc::SIGSEGV)
+        );
+        assert_eq!(
+            signal_in_child(|| unsafe { ptr.add(len).write_volatile(1) }),
+            Some(libc::SIGSEGV)
+        );
+    }
+
+    // Both views share the same memory
+    unsafe { s1.as_ptr().write_volatile(0xAB) };
+    assert_eq!(unsafe { s2.as_ptr().read_volatile() }, 0xAB);
+}
+
+#[test]
+fn guard_pages_tmpfs() {
+    let s = ShmemConf::new()
+        .size(100)
+        .use_tmpfs_with_dir("/tmp")
+        .guard_pages(1)
+        .create()
+        .unwrap();
+    let ptr = s.as_ptr();
+    assert_eq!(
+        signal_in_child(|| unsafe { ptr.sub(1).write_volatile(1) }),
+        Some(libc::SIGSEGV)
+    );
+}
+
+#[test]
+fn guard_pages_overflow() {
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    // Overflows when computing the size of one guard region, then when adding both of them
+    for count in [usize::MAX / page + 2, usize::MAX / page / 2 + 1] {
+        assert!(matches!(
+            ShmemConf::new().size(4096).guard_pages(count).create(),
+            Err(ShmemError::MapSizeOverflow)
+        ));
+        assert!(matches!(
+            ShmemConf::new().size(4096).guard_pages(count).anonymous(),
+            Err(ShmemError::MapSizeOverflow)
+        ));
+    }
+
+    let s = ShmemConf::new().size(4096).create().unwrap();
+    assert!(matches!(
+        ShmemConf::new()
+            .os_id(s.get_os_id())
+            .guard_pages(usize::MAX / page)
+            .open(),
+        Err(ShmemError::MapSizeOverflow)
+    ));
+}
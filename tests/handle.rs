@@ -0,0 +1,67 @@
+use std::thread;
+
+use shared_memory::{Shmem, ShmemConf, ShmemHandle};
+
+// Fails to compile if the types lose their auto traits
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Shmem>();
+    assert_send_sync::<ShmemHandle>();
+};
+
+#[test]
+fn move_to_thread() {
+    let s = ShmemConf::new().size(4096).create().unwrap();
+    let os_id = s.get_os_id().to_string();
+
+    let s = thread::spawn(move || {
+        unsafe { s.as_ptr().write_volatile(0xAB) };
+        s
+    })
+    .join()
+    .unwrap();
+    assert!(s.is_owner());
+    assert_eq!(s.get_os_id(), os_id);
+    assert_eq!(unsafe { s.as_ptr().read_volatile() }, 0xAB);
+}
+
+#[test]
+fn handle_shared_between_threads() {
+    let handle = ShmemConf::new().size(4096).create().unwrap().into_handle();
+    let os_id = handle.get_os_id().to_string();
+
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let handle = handle.clone();
+            thread::spawn(move || unsafe { handle.as_ptr().add(i).write_volatile(i as u8 + 1) })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    for i in 0..4 {
+        assert_eq!(
+            unsafe { handle.as_ptr().add(i).read_volatile() },
+            i as u8 + 1
+        );
+    }
+
+    // The mapping stays around until the last handle is dropped
+    let other = handle.clone();
+    assert_eq!(ShmemHandle::handle_count(&handle), 2);
+    drop(handle);
+    assert!(ShmemConf::new().os_id(&os_id).open().is_ok());
+    drop(other);
+    assert!(ShmemConf::new().os_id(&os_id).open().is_err());
+}
+
+#[test]
+fn handle_try_unwrap() {
+    let handle = ShmemHandle::from(ShmemConf::new().size(4096).create().unwrap());
+    let other = handle.clone();
+
+    let handle = ShmemHandle::try_unwrap(handle).err().unwrap();
+    drop(other);
+    let mut s = ShmemHandle::try_unwrap(handle).ok().unwrap();
+    assert!(s.set_owner(true));
+}
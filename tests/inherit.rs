@@ -0,0 +1,122 @@
+#![cfg(not(target_os = "windows"))]
+
+use std::process::Command;
+
+use shared_memory::{PosixShmBackend, Shmem, ShmemBackend, ShmemCommandExt, ShmemConf, ShmemError};
+
+const ENV_NAME: &str = "SHMEM_INHERIT_TEST";
+
+/// Runs `child_main` in a new process that inherits `shmem`
+fn spawn_child(shmem: &Shmem) {
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "child_main", "--nocapture", "--test-threads=1"])
+        .inherit_shmem(ENV_NAME, shmem)
+        .unwrap()
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+/// Entry point of the spawned processes, does nothing when run as a regular test
+#[test]
+fn child_main() {
+    if std::env::var(ENV_NAME).is_err() {
+        return;
+    }
+    let mut s = Shmem::from_inherited(ENV_NAME).unwrap();
+    assert!(!s.is_owner());
+    // The mapping can only be taken once
+    assert!(matches!(
+        Shmem::from_inherited(ENV_NAME),
+        Err(ShmemError::NotInherited)
+    ));
+    let data = unsafe { s.as_slice_mut() };
+    assert_eq!(data[0], 0xAB);
+    data[1] = 0xCD;
+}
+
+#[test]
+fn inherit_named() {
+    let mut s = ShmemConf::new().size(4096).create().unwrap();
+    unsafe { s.as_slice_mut()[0] = 0xAB };
+    spawn_child(&s);
+    assert_eq!(unsafe { s.as_slice() }[1], 0xCD);
+}
+
+#[test]
+fn inherit_dropped_before_spawn() {
+    let mut s = ShmemConf::new().size(4096).create().unwrap();
+    unsafe { s.as_slice_mut()[0] = 0xAB };
+    let mut cmd = Command::new(std::env::current_exe().unwrap());
+    cmd.args(["--exact", "child_main", "--nocapture", "--test-threads=1"])
+        .inherit_shmem(ENV_NAME, &s)
+        .unwrap();
+
+    // The command keeps the mapping reachable, an unrelated file may now reuse the number of its descriptor
+    let os_id = s.get_os_id().to_string();
+    s.set_owner(false);
+    drop(s);
+    let _f = std::fs::File::open("/dev/null").unwrap();
+    assert!(cmd.status().unwrap().success());
+
+    let s = ShmemConf::new().os_id(&os_id).open().unwrap();
+    assert_eq!(unsafe { s.as_slice() }[1], 0xCD);
+    PosixShmBackend.unlink(&os_id).unwrap();
+}
+
+#[test]
+fn inherit_unlinked() {
+    let mut s = ShmemConf::new()
+        .size(4096)
+        .shared_ownership(true)
+        .create()
+        .unwrap();
+    PosixShmBackend.unlink(s.get_os_id()).unwrap();
+    unsafe { s.as_slice_mut()[0] = 0xAB };
+    spawn_child(&s);
+    assert_eq!(unsafe { s.as_slice() }[1], 0xCD);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn inherit_memfd() {
+    let mut s = ShmemConf::new()
+        .size(4096)
+        .backend(shared_memory::MemfdBackend)
+        .create()
+        .unwrap();
+    unsafe { s.as_slice_mut()[0] = 0xAB };
+    spawn_child(&s);
+    assert_eq!(unsafe { s.as_slice() }[1], 0xCD);
+}
+
+#[test]
+fn inherit_errors() {
+    assert!(matches!(
+        Shmem::from_inherited("SHMEM_INHERIT_MISSING"),
+        Err(ShmemError::NotInherited)
+    ));
+
+    let s = ShmemConf::new().size(4096).anonymous().unwrap();
+    assert!(matches!(
+        Command::new("true").inherit_shmem(ENV_NAME, &s),
+        Err(ShmemError::Unsupported)
+    ));
+}
+
+#[test]
+fn inherit_wrong_fd() {
+    use std::os::fd::AsRawFd;
+
+    // The variable names a descriptor that is open but is not the described file
+    let f = std::fs::File::open("/dev/null").unwrap();
+    let name = "SHMEM_INHERIT_WRONG_FD";
+    std::env::set_var(name, format!("{}:4096:0:0:0:/wrong", f.as_raw_fd()));
+    assert!(matches!(
+        Shmem::from_inherited(name),
+        Err(ShmemError::NotInherited)
+    ));
+
+    // The descriptor was left alone
+    assert_ne!(unsafe { libc::fcntl(f.as_raw_fd(), libc::F_GETFD) }, -1);
+}
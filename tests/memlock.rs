@@ -0,0 +1,81 @@
+#![cfg(not(target_os = "windows"))]
+
+use shared_memory::{ShmemConf, ShmemError};
+
+/// Returns how many pages of the mapping are resident in memory
+#[cfg(target_os = "linux")]
+fn resident_pages(ptr: *mut u8, len: usize) -> usize {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+    let mut vec = vec![0u8; len.div_ceil(page_size)];
+    assert_eq!(
+        unsafe { libc::mincore(ptr as *mut _, len, vec.as_mut_ptr()) },
+        0
+    );
+    vec.iter().filter(|v| *v & 1 != 0).count()
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn populate() {
+    let s1 = ShmemConf::new().size(64 * 4096).create().unwrap();
+    assert_eq!(resident_pages(s1.as_ptr(), s1.len()), 0);
+
+    let s2 = ShmemConf::new()
+        .size(64 * 4096)
+        .populate(true)
+        .create()
+        .unwrap();
+    assert_eq!(resident_pages(s2.as_ptr(), s2.len()), 64);
+
+    // Opening populates our view of an existing mapping as well
+    let s3 = ShmemConf::new()
+        .os_id(s1.get_os_id())
+        .populate(true)
+        .open()
+        .unwrap();
+    assert_eq!(resident_pages(s3.as_ptr(), s3.len()), 64);
+}
+
+#[test]
+fn lock_unlock() {
+    let s = ShmemConf::new().size(4096).populate(true).create().unwrap();
+    s.lock().unwrap();
+    unsafe { s.as_ptr().write_volatile(1) };
+    s.unlock().unwrap();
+}
+
+#[test]
+fn lock_over_limit() {
+    let s = ShmemConf::new().size(4096).create().unwrap();
+
+    // Lower the limit in a child so the test process is not affected
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        let limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        let code = if unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &limit) } != 0 {
+            3
+        } else if unsafe { libc::geteuid() } == 0
+            // Privileged processes ignore the limit, switching to an unprivileged user drops
+            // CAP_IPC_LOCK along with every other capability
+            && unsafe { libc::setgid(65534) != 0 || libc::setuid(65534) != 0 }
+        {
+            4
+        } else {
+            match s.lock() {
+                Err(ShmemError::MemlockLimitExceeded(0)) => 0,
+                Ok(_) => 1,
+                Err(_) => 2,
+            }
+        };
+        unsafe { libc::_exit(code) };
+    }
+
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFEXITED(status));
+    assert_eq!(libc::WEXITSTATUS(status), 0);
+}
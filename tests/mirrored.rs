@@ -0,0 +1,107 @@
+#![cfg(not(target_os = "windows"))]
+
+use shared_memory::{Protection, ShmemConf, ShmemError};
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+#[test]
+fn mirrored_wraps_around() {
+    let s1 = ShmemConf::new().size(100).mirrored(true).create().unwrap();
+    // Rounded up so the mirror starts on a page boundary
+    assert_eq!(s1.len(), page_size());
+
+    let s2 = ShmemConf::new()
+        .os_id(s1.get_os_id())
+        .mirrored(true)
+        .open()
+        .unwrap();
+    let len = s1.len();
+
+    // A write running past the end lands at the start
+    let src = [1u8, 2, 3, 4];
+    unsafe { std::ptr::copy_nonoverlapping(src.as_ptr(), s1.as_ptr().add(len - 2), src.len()) };
+    assert_eq!(
+        unsafe { std::slice::from_raw_parts(s2.as_ptr(), 2) },
+        &[3, 4]
+    );
+    assert_eq!(
+        unsafe { std::slice::from_raw_parts(s2.as_ptr().add(len - 2), 4) },
+        &src
+    );
+
+    // The whole mapping can be read from any offset
+    let wrapped = unsafe { std::slice::from_raw_parts(s2.as_ptr().add(len - 1), len) };
+    assert_eq!(wrapped[0], 2);
+    assert_eq!(wrapped[1], 3);
+}
+
+#[test]
+fn mirrored_with_guard_pages() {
+    let s = ShmemConf::new()
+        .size(2 * page_size())
+        .mirrored(true)
+        .guard_pages(1)
+        .create()
+        .unwrap();
+    unsafe { s.as_ptr().add(s.len()).write_volatile(0xAB) };
+    assert_eq!(unsafe { s.as_ptr().read_volatile() }, 0xAB);
+}
+
+#[test]
+fn mirrored_protect_covers_mirror() {
+    let mut s = ShmemConf::new()
+        .size(page_size())
+        .mirrored(true)
+        .create()
+        .unwrap();
+    s.protect(0..page_size(), Protection::ReadOnly).unwrap();
+
+    // Writing through the mirror faults like writing to the first view would
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        unsafe {
+            s.as_ptr().add(s.len()).write_volatile(1);
+            libc::_exit(0);
+        }
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFSIGNALED(status));
+}
+
+#[test]
+fn mirrored_open_unaligned() {
+    let s = ShmemConf::new().size(100).create().unwrap();
+    assert!(matches!(
+        ShmemConf::new().os_id(s.get_os_id()).mirrored(true).open(),
+        Err(ShmemError::MapSizeMismatch(100))
+    ));
+}
+
+#[test]
+fn mirrored_unsupported() {
+    assert!(matches!(
+        ShmemConf::new()
+            .size(4096)
+            .mirrored(true)
+            .shared_ownership(true)
+            .create(),
+        Err(ShmemError::Unsupported)
+    ));
+    assert!(matches!(
+        ShmemConf::new().size(4096).mirrored(true).anonymous(),
+        Err(ShmemError::Unsupported)
+    ));
+
+    let s = ShmemConf::new().size(4096).create().unwrap();
+    assert!(matches!(
+        ShmemConf::new()
+            .os_id(s.get_os_id())
+            .mirrored(true)
+            .open_private(),
+        Err(ShmemError::Unsupported)
+    ));
+}
@@ -0,0 +1,208 @@
+#![cfg(not(target_os = "windows"))]
+
+use shared_memory::{ShmemConf, ShmemError};
+
+#[test]
+fn namespace_generated_os_id() {
+    let namespace = format!("ns_os_id_{}", std::process::id());
+    let s = ShmemConf::new()
+        .size(4096)
+        .namespace(&namespace)
+        .create()
+        .unwrap();
+    assert!(s.get_os_id().starts_with(&format!("/{namespace}.")));
+
+    // Explicit os_ids are not affected
+    let os_id = format!("/ns_explicit_{}", std::process::id());
+    let s = ShmemConf::new()
+        .size(4096)
+        .namespace(&namespace)
+        .os_id(&os_id)
+        .create()
+        .unwrap();
+    assert_eq!(s.get_os_id(), os_id);
+}
+
+#[test]
+fn namespace_tmpfs_subdirectory() {
+    let namespace = format!("ns_tmpfs_{}", std::process::id());
+    let conf = ShmemConf::new()
+        .use_tmpfs_with_dir("/tmp")
+        .namespace(&namespace);
+
+    let s1 = conf.clone().size(4096).create().unwrap();
+    let s2 = conf.clone().size(4096).os_id("named").create().unwrap();
+    let path = s2.get_tmpfs_file_path().unwrap();
+    assert_eq!(
+        path,
+        std::path::Path::new("/tmp").join(&namespace).join("named")
+    );
+    assert!(path.is_file());
+    assert!(s1.get_os_id().starts_with(&format!("/tmp/{namespace}/")));
+
+    let mut listed = conf.list().unwrap();
+    assert_eq!(listed.len(), 2);
+    assert!(listed.contains(&String::from("named")));
+
+    // Listed os_ids can be opened within the namespace
+    listed.retain(|v| v != "named");
+    let s3 = conf.clone().os_id(&listed[0]).open().unwrap();
+    unsafe { s1.as_ptr().write_volatile(0xAB) };
+    assert_eq!(unsafe { s3.as_ptr().read_volatile() }, 0xAB);
+
+    drop((s1, s2, s3));
+    assert!(conf.list().unwrap().is_empty());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn namespace_list_and_cleanup() {
+    let namespace = format!("ns_cleanup_{}", std::process::id());
+    let conf = ShmemConf::new().namespace(&namespace);
+    assert!(conf.list().unwrap().is_empty());
+
+    // Leak two mappings as if their owner had crashed
+    let mut os_ids = Vec::new();
+    for _ in 0..2 {
+        let mut s = conf.clone().size(4096).create().unwrap();
+        s.set_owner(false);
+        os_ids.push(String::from(s.get_os_id()));
+    }
+    let _other = ShmemConf::new().size(4096).create().unwrap();
+    os_ids.sort();
+    assert_eq!(conf.list().unwrap(), os_ids);
+
+    assert_eq!(conf.cleanup().unwrap(), 2);
+    assert!(conf.list().unwrap().is_empty());
+    assert!(matches!(
+        ShmemConf::new().os_id(&os_ids[0]).open(),
+        Err(ShmemError::MapOpenFailed(_))
+    ));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn namespace_nested_looking() {
+    let outer = ShmemConf::new().namespace(format!("ns_outer_{}", std::process::id()));
+    let inner = ShmemConf::new().namespace(format!("ns_outer_{}.app", std::process::id()));
+
+    let s1 = outer.clone().size(4096).create().unwrap();
+    let s2 = inner.clone().size(4096).create().unwrap();
+    assert_eq!(outer.list().unwrap(), [s1.get_os_id()]);
+    assert_eq!(inner.list().unwrap(), [s2.get_os_id()]);
+
+    // Cleaning up the outer namespace leaves the inner one alone
+    assert_eq!(outer.cleanup().unwrap(), 1);
+    assert_eq!(inner.list().unwrap(), [s2.get_os_id()]);
+    assert!(ShmemConf::new().os_id(s2.get_os_id()).open().is_ok());
+}
+
+#[test]
+fn namespace_validation() {
+    for namespace in ["", ".", "..", "a/b"] {
+        assert!(matches!(
+            ShmemConf::new().size(4096).namespace(namespace).create(),
+            Err(ShmemError::InvalidNamespace)
+        ));
+    }
+    assert!(matches!(
+        ShmemConf::new().list(),
+        Err(ShmemError::NoNamespace)
+    ));
+}
+
+#[test]
+fn key_derived_os_id() {
+    let key = format!("key_derived_{}", std::process::id());
+    let s1 = ShmemConf::new().size(4096).key(&key).create().unwrap();
+    assert!(s1.get_os_id().starts_with("/shmem_"));
+    unsafe { s1.as_ptr().write_volatile(0xAB) };
+
+    // The same key finds the same mapping
+    let s2 = ShmemConf::new().key(&key).open().unwrap();
+    assert_eq!(s2.get_os_id(), s1.get_os_id());
+    assert_eq!(unsafe { s2.as_ptr().read_volatile() }, 0xAB);
+
+    // The namespace is part of the derivation
+    let namespace = format!("ns_key_{}", std::process::id());
+    let s3 = ShmemConf::new()
+        .size(4096)
+        .namespace(&namespace)
+        .key(&key)
+        .create()
+        .unwrap();
+    assert!(s3.get_os_id().starts_with(&format!("/{namespace}.")));
+    assert_eq!(
+        ShmemConf::new().namespace(&namespace).list().unwrap(),
+        vec![String::from(s3.get_os_id())]
+    );
+
+    // Keys of any length and content make valid names
+    let long_key = vec![b'/'; 4096];
+    let s4 = ShmemConf::new().size(4096).key(&long_key).create().unwrap();
+    assert_ne!(s4.get_os_id(), s1.get_os_id());
+    let s5 = ShmemConf::new().key(&long_key).open().unwrap();
+    assert_eq!(s5.get_os_id(), s4.get_os_id());
+}
+
+#[test]
+fn key_derived_tmpfs_path() {
+    let key = format!("key_tmpfs_{}", std::process::id());
+    let namespace = format!("ns_key_tmpfs_{}", std::process::id());
+    let conf = ShmemConf::new()
+        .use_tmpfs_with_dir("/tmp")
+        .namespace(&namespace)
+        .key(&key);
+
+    let s1 = conf.clone().size(4096).create().unwrap();
+    let path = s1.get_tmpfs_file_path().unwrap();
+    assert_eq!(
+        path.parent().unwrap(),
+        std::path::Path::new("/tmp").join(&namespace)
+    );
+    assert!(path.is_file());
+
+    let s2 = conf.open().unwrap();
+    assert_eq!(s2.get_os_id(), s1.get_os_id());
+}
+
+#[test]
+fn key_with_os_id() {
+    assert!(matches!(
+        ShmemConf::new()
+            .size(4096)
+            .key("key_with_os_id")
+            .os_id("explicit")
+            .create(),
+        Err(ShmemError::KeyWithOsId)
+    ));
+    assert!(matches!(
+        ShmemConf::new()
+            .key("key_with_os_id")
+            .os_id("explicit")
+            .open(),
+        Err(ShmemError::KeyWithOsId)
+    ));
+}
+
+#[test]
+fn namespace_too_long() {
+    // Generated os_ids must fit in a shm_open() name
+    let namespace = "n".repeat(256);
+    assert!(matches!(
+        ShmemConf::new().size(4096).namespace(&namespace).create(),
+        Err(ShmemError::InvalidNamespace)
+    ));
+    assert!(matches!(
+        ShmemConf::new()
+            .size(4096)
+            .namespace(&namespace)
+            .key("namespace_too_long")
+            .create(),
+        Err(ShmemError::InvalidNamespace)
+    ));
+    assert!(matches!(
+        ShmemConf::new().namespace(&namespace).list(),
+        Err(ShmemError::InvalidNamespace)
+    ));
+}
@@ -0,0 +1,131 @@
+use std::sync::mpsc::channel;
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
+
+use shared_memory::{ShmemConf, ShmemError};
+
+/// Creates the mapping of `conf` after a delay and keeps it alive until told otherwise
+fn create_later(conf: ShmemConf) -> impl FnOnce() {
+    let (tx, rx) = channel::<()>();
+    let handle = spawn(move || {
+        sleep(Duration::from_millis(200));
+        let _s = conf.size(4096).create().unwrap();
+        let _ = rx.recv();
+    });
+    move || {
+        drop(tx);
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn open_wait_os_id() {
+    let os_id = format!("/open_wait_{}", std::process::id());
+    let done = create_later(ShmemConf::new().os_id(&os_id).shared_ownership(true));
+
+    let start = Instant::now();
+    let s = ShmemConf::new()
+        .os_id(&os_id)
+        .shared_ownership(true)
+        .open_wait(Duration::from_secs(10))
+        .unwrap();
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(s.len(), 4096);
+    done();
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn open_wait_flink() {
+    let dir = std::env::temp_dir().join(format!("open_wait_flink_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let flink = dir.join("flink");
+    let done = create_later(ShmemConf::new().flink(&flink));
+
+    let s = ShmemConf::new()
+        .flink(&flink)
+        .open_wait(Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(s.len(), 4096);
+    done();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn open_wait_forever() {
+    let os_id = format!("/open_wait_forever_{}", std::process::id());
+    let done = create_later(ShmemConf::new().os_id(&os_id));
+
+    // Too large to be added to the current time
+    let s = ShmemConf::new()
+        .os_id(&os_id)
+        .open_wait(Duration::MAX)
+        .unwrap();
+    assert_eq!(s.len(), 4096);
+    done();
+}
+
+#[test]
+fn open_wait_timeout() {
+    let os_id = format!("/open_wait_timeout_{}", std::process::id());
+    let start = Instant::now();
+    assert!(matches!(
+        ShmemConf::new()
+            .os_id(&os_id)
+            .open_wait(Duration::from_millis(100)),
+        Err(ShmemError::Timeout)
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // Other errors are returned right away
+    assert!(matches!(
+        ShmemConf::new()
+            .os_id("a/b")
+            .open_wait(Duration::from_secs(10)),
+        Err(ShmemError::InvalidOsId)
+    ));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn open_wait_async() {
+    let os_id = format!("/open_wait_async_{}", std::process::id());
+    let done = create_later(ShmemConf::new().os_id(&os_id));
+
+    let s = ShmemConf::new()
+        .os_id(&os_id)
+        .open_wait_async(Duration::from_secs(10))
+        .await
+        .unwrap();
+    assert_eq!(s.len(), 4096);
+    done();
+
+    assert!(matches!(
+        ShmemConf::new()
+            .os_id(&os_id)
+            .open_wait_async(Duration::from_millis(100))
+            .await,
+        Err(ShmemError::Timeout)
+    ));
+}
+
+#[cfg(all(feature = "tokio", not(target_os = "windows")))]
+#[tokio::test]
+async fn open_wait_async_partial_flink() {
+    let dir = std::env::temp_dir().join(format!("open_wait_async_flink_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let flink = dir.join("flink");
+
+    // The flink exists but does not name a mapping yet
+    std::fs::write(&flink, "").unwrap();
+    let done = create_later(ShmemConf::new().flink(&flink).force_create_flink());
+
+    let s = ShmemConf::new()
+        .flink(&flink)
+        .open_wait_async(Duration::MAX)
+        .await
+        .unwrap();
+    assert_eq!(s.len(), 4096);
+    done();
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,90 @@
+#![cfg(not(target_os = "windows"))]
+
+use std::io::Write;
+
+use shared_memory::{ShmemConf, ShmemError};
+
+#[test]
+fn os_id_normalization() {
+    let name = format!("os_id_norm_{}", std::process::id());
+
+    // A leading '/' is added for shm_open()
+    let s1 = ShmemConf::new().size(4096).os_id(&name).create().unwrap();
+    assert_eq!(s1.get_os_id(), format!("/{name}"));
+    let s2 = ShmemConf::new().os_id(format!("/{name}")).open().unwrap();
+    assert_eq!(s2.get_os_id(), s1.get_os_id());
+
+    // And removed in tmpfs mode
+    let s3 = ShmemConf::new()
+        .size(4096)
+        .use_tmpfs_with_dir("/tmp")
+        .os_id(format!("/{name}"))
+        .create()
+        .unwrap();
+    assert_eq!(
+        s3.get_tmpfs_file_path().unwrap(),
+        std::path::Path::new("/tmp").join(&name)
+    );
+}
+
+#[test]
+fn os_id_validation() {
+    let too_long = "a".repeat(256);
+    for os_id in [
+        "",
+        "/",
+        ".",
+        "..",
+        "/..",
+        "a/b",
+        "../escape",
+        "a\\b",
+        "a\0b",
+        &too_long,
+    ] {
+        assert!(matches!(
+            ShmemConf::new().size(4096).os_id(os_id).create(),
+            Err(ShmemError::InvalidOsId)
+        ));
+        assert!(matches!(
+            ShmemConf::new().os_id(os_id).open(),
+            Err(ShmemError::InvalidOsId)
+        ));
+        assert!(matches!(
+            ShmemConf::new()
+                .size(4096)
+                .use_tmpfs_with_dir("/tmp")
+                .os_id(os_id)
+                .create(),
+            Err(ShmemError::InvalidOsId)
+        ));
+    }
+}
+
+#[test]
+fn os_id_flink_validation() {
+    let dir = std::env::temp_dir().join(format!("os_id_flink_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let flink = dir.join("flink");
+
+    for content in ["../escape", "/tmp/escape"] {
+        std::fs::File::create(&flink)
+            .unwrap()
+            .write_all(content.as_bytes())
+            .unwrap();
+        assert!(matches!(
+            ShmemConf::new().flink(&flink).open(),
+            Err(ShmemError::InvalidOsId)
+        ));
+        // tmpfs flinks must point inside the tmpfs directory
+        assert!(matches!(
+            ShmemConf::new()
+                .use_tmpfs_with_dir(&dir)
+                .flink(&flink)
+                .open(),
+            Err(ShmemError::InvalidOsId)
+        ));
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
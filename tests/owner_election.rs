@@ -0,0 +1,285 @@
+#![cfg(not(target_os = "windows"))]
+
+use shared_memory::ShmemConf;
+
+/// Blocks until a byte can be read from `fd`, returns `None` on EOF
+fn read_byte(fd: i32) -> Option<u8> {
+    let mut b = 0u8;
+    match unsafe { libc::read(fd, &mut b as *mut u8 as _, 1) } {
+        1 => Some(b),
+        _ => None,
+    }
+}
+
+fn write_byte(fd: i32, b: u8) {
+    assert_eq!(unsafe { libc::write(fd, &b as *const u8 as _, 1) }, 1);
+}
+
+fn pipe() -> (i32, i32) {
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    (fds[0], fds[1])
+}
+
+fn fork(child: impl FnOnce() -> i32) -> libc::pid_t {
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        let code = child();
+        unsafe { libc::_exit(code) };
+    }
+    pid
+}
+
+fn wait(pid: libc::pid_t) -> i32 {
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    status
+}
+
+#[test]
+fn elect_after_owner_killed() {
+    let os_id = format!("/shmem_election_{:X}", std::process::id());
+    let (events_rx, events_tx) = pipe();
+    let (killed_rx, killed_tx) = pipe();
+    let (exit_rx, exit_tx) = pipe();
+
+    // The owner creates the mapping and waits to be killed
+    let owner = fork(|| {
+        let _shmem = match ShmemConf::new()
+            .size(4096)
+            .os_id(&os_id)
+            .owner_election(true)
+            .create()
+        {
+            Ok(s) => s,
+            Err(_) => return 1,
+        };
+        write_byte(events_tx, b'c');
+        loop {
+            unsafe { libc::pause() };
+        }
+    });
+    assert_eq!(read_byte(events_rx), Some(b'c'));
+
+    // Two survivors attach to the mapping
+    let mut survivors = Vec::new();
+    for _ in 0..2 {
+        survivors.push(fork(|| {
+            // Survivors are signaled by the parent closing its end of the pipes
+            unsafe {
+                libc::close(killed_tx);
+                libc::close(exit_tx);
+            }
+            let mut shmem = match ShmemConf::new().os_id(&os_id).owner_election(true).open() {
+                Ok(s) => s,
+                Err(_) => return 1,
+            };
+            // The owner is still alive
+            if shmem.is_owner() || shmem.elect_owner().unwrap() {
+                return 2;
+            }
+            write_byte(events_tx, b'a');
+
+            // Wait for the owner to be killed and race to take over
+            let _ = read_byte(killed_rx);
+            let won = shmem.elect_owner().unwrap();
+            write_byte(events_tx, if won { b'w' } else { b'l' });
+
+            // Wait for the go-ahead to exit, dropping the mapping
+            let _ = read_byte(exit_rx);
+            drop(shmem);
+            0
+        }));
+    }
+    assert_eq!(read_byte(events_rx), Some(b'a'));
+    assert_eq!(read_byte(events_rx), Some(b'a'));
+
+    // Kill the owner without giving it a chance to clean up
+    unsafe { libc::kill(owner, libc::SIGKILL) };
+    wait(owner);
+
+    unsafe { libc::close(killed_tx) };
+    let mut results = vec![read_byte(events_rx).unwrap(), read_byte(events_rx).unwrap()];
+    results.sort_unstable();
+    // Exactly one survivor took over
+    assert_eq!(results, vec![b'l', b'w']);
+
+    // The mapping must still exist while survivors are attached
+    let third = ShmemConf::new()
+        .os_id(&os_id)
+        .owner_election(true)
+        .open()
+        .unwrap();
+    assert!(!third.is_owner());
+
+    // Let the survivors exit
+    unsafe { libc::close(exit_tx) };
+    for pid in survivors {
+        let status = wait(pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+    }
+
+    // The new owner left the mapping to the process still attached
+    assert!(!third.is_owner());
+    let fourth = ShmemConf::new()
+        .os_id(&os_id)
+        .owner_election(true)
+        .open()
+        .unwrap();
+    drop(fourth);
+
+    // The last process attached cleans up the mapping
+    drop(third);
+    assert!(ShmemConf::new()
+        .os_id(&os_id)
+        .owner_election(true)
+        .open()
+        .is_err());
+}
+
+#[test]
+fn elect_after_forking_owner_killed() {
+    let os_id = format!("/shmem_election_fork_{:X}", std::process::id());
+    let (events_rx, events_tx) = pipe();
+    let (exit_rx, exit_tx) = pipe();
+
+    // The owner forks a child that inherits its descriptors and outlives it
+    let owner = fork(|| {
+        let _shmem = match ShmemConf::new()
+            .size(4096)
+            .os_id(&os_id)
+            .owner_election(true)
+            .create()
+        {
+            Ok(s) => s,
+            Err(_) => return 1,
+        };
+        fork(|| {
+            unsafe { libc::close(exit_tx) };
+            write_byte(events_tx, b'f');
+            let _ = read_byte(exit_rx);
+            0
+        });
+        write_byte(events_tx, b'c');
+        loop {
+            unsafe { libc::pause() };
+        }
+    });
+    let mut events = vec![read_byte(events_rx).unwrap(), read_byte(events_rx).unwrap()];
+    events.sort_unstable();
+    assert_eq!(events, vec![b'c', b'f']);
+
+    let mut shmem = ShmemConf::new()
+        .os_id(&os_id)
+        .owner_election(true)
+        .open()
+        .unwrap();
+    assert!(!shmem.elect_owner().unwrap());
+
+    unsafe { libc::kill(owner, libc::SIGKILL) };
+    wait(owner);
+
+    // The child of the owner does not keep it alive
+    assert!(shmem.elect_owner().unwrap());
+    drop(shmem);
+    assert!(ShmemConf::new()
+        .os_id(&os_id)
+        .owner_election(true)
+        .open()
+        .is_err());
+    unsafe { libc::close(exit_tx) };
+}
+
+#[test]
+fn forked_child_leaves_mapping_alone() {
+    let shmem = ShmemConf::new()
+        .size(4096)
+        .owner_election(true)
+        .create()
+        .unwrap();
+
+    // The child inherits the mapping but must neither clean it up nor release ownership
+    let child = unsafe { libc::fork() };
+    assert!(child >= 0);
+    if child == 0 {
+        drop(shmem);
+        unsafe { libc::_exit(0) };
+    }
+    assert_eq!(libc::WEXITSTATUS(wait(child)), 0);
+
+    assert!(shmem.is_owner());
+    let mut other = ShmemConf::new()
+        .os_id(shmem.get_os_id())
+        .owner_election(true)
+        .open()
+        .unwrap();
+    assert!(!other.elect_owner().unwrap());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn last_process_cleans_up() {
+    let s1 = ShmemConf::new()
+        .size(4096)
+        .owner_election(true)
+        .create()
+        .unwrap();
+    let os_id = s1.get_os_id().to_string();
+    let mut s2 = ShmemConf::new()
+        .os_id(&os_id)
+        .owner_election(true)
+        .open()
+        .unwrap();
+    let s3 = ShmemConf::new()
+        .os_id(&os_id)
+        .owner_election(true)
+        .open()
+        .unwrap();
+
+    // The owner leaves first without removing the mapping
+    drop(s1);
+    assert!(!s2.is_owner());
+    assert!(ShmemConf::new()
+        .os_id(&os_id)
+        .owner_election(true)
+        .open()
+        .is_ok());
+
+    // A survivor can take over and leave again
+    assert!(s2.elect_owner().unwrap());
+    drop(s2);
+    assert!(!s3.is_owner());
+
+    drop(s3);
+    assert!(ShmemConf::new()
+        .os_id(&os_id)
+        .owner_election(true)
+        .open()
+        .is_err());
+}
+
+#[test]
+fn no_election_while_owner_alive() {
+    let mut s1 = ShmemConf::new()
+        .size(4096)
+        .owner_election(true)
+        .create()
+        .unwrap();
+    let mut s2 = ShmemConf::new()
+        .os_id(s1.get_os_id())
+        .owner_election(true)
+        .open()
+        .unwrap();
+
+    assert!(s1.is_owner());
+    assert!(!s2.elect_owner().unwrap());
+    assert!(!s2.is_owner());
+
+    // Once the owner is gone, anyone can take over
+    s1.set_owner(false);
+    assert!(s2.elect_owner().unwrap());
+    assert!(s2.is_owner());
+    assert!(!s1.is_owner());
+}
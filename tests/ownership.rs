@@ -0,0 +1,111 @@
+use shared_memory::{ShmemConf, ShmemError};
+
+#[test]
+fn shared_ownership_create_open() {
+    let s1 = ShmemConf::new()
+        .size(4096)
+        .shared_ownership(true)
+        .create()
+        .unwrap();
+    let s2 = ShmemConf::new()
+        .os_id(s1.get_os_id())
+        .shared_ownership(true)
+        .open()
+        .unwrap();
+
+    assert!(s1.is_owner());
+    assert!(!s2.is_owner());
+    // The header is not part of the user visible mapping
+    assert_eq!(s1.len(), 4096);
+    assert_eq!(s2.len(), s1.len());
+    assert_ne!(s1.as_ptr(), s2.as_ptr());
+}
+
+#[test]
+fn shared_ownership_handoff() {
+    let mut s1 = ShmemConf::new()
+        .size(4096)
+        .shared_ownership(true)
+        .create()
+        .unwrap();
+    let os_id = s1.get_os_id().to_string();
+    let mut s2 = ShmemConf::new()
+        .os_id(&os_id)
+        .shared_ownership(true)
+        .open()
+        .unwrap();
+
+    // Only the owner can hand off ownership
+    assert!(matches!(
+        s2.release_ownership_to(std::process::id()),
+        Err(ShmemError::NotOwner)
+    ));
+
+    s1.release_ownership_to(std::process::id()).unwrap();
+    assert!(!s1.is_owner());
+    assert!(!s2.is_owner());
+
+    s2.claim_ownership().unwrap();
+    assert!(!s1.is_owner());
+    assert!(s2.is_owner());
+
+    // The previous owner must not delete the mapping
+    drop(s1);
+    let s3 = ShmemConf::new()
+        .os_id(&os_id)
+        .shared_ownership(true)
+        .open()
+        .unwrap();
+
+    // The new owner deletes it
+    drop(s2);
+    assert!(ShmemConf::new()
+        .os_id(&os_id)
+        .shared_ownership(true)
+        .open()
+        .is_err());
+    drop(s3);
+}
+
+#[test]
+fn shared_ownership_claim_refused() {
+    let mut s1 = ShmemConf::new()
+        .size(4096)
+        .shared_ownership(true)
+        .create()
+        .unwrap();
+    let mut s2 = ShmemConf::new()
+        .os_id(s1.get_os_id())
+        .shared_ownership(true)
+        .open()
+        .unwrap();
+
+    // Handing off to another pid leaves us unable to claim it
+    s1.release_ownership_to(u32::MAX).unwrap();
+    assert!(matches!(
+        s2.claim_ownership(),
+        Err(ShmemError::OwnershipClaimFailed)
+    ));
+    assert!(!s2.is_owner());
+
+    // Cleanup
+    s2.set_owner(true);
+}
+
+#[test]
+fn shared_ownership_requires_header() {
+    let s1 = ShmemConf::new().size(4096).create().unwrap();
+    assert!(matches!(
+        ShmemConf::new()
+            .os_id(s1.get_os_id())
+            .shared_ownership(true)
+            .open(),
+        Err(ShmemError::InvalidHeader)
+    ));
+
+    let mut s2 = ShmemConf::new().os_id(s1.get_os_id()).open().unwrap();
+    assert!(matches!(
+        s2.claim_ownership(),
+        Err(ShmemError::NoSharedOwnership)
+    ));
+}
@@ -0,0 +1,102 @@
+#![cfg(not(target_os = "windows"))]
+
+use std::path::PathBuf;
+
+use shared_memory::{ShmemConf, ShmemError};
+
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn persistent_survives_owner() {
+    let dir = test_dir("persistent_survives_owner");
+    let os_id = "persistent_data";
+
+    let s1 = ShmemConf::new()
+        .size(4096)
+        .persistent_with_dir(&dir)
+        .os_id(os_id)
+        .create()
+        .unwrap();
+    assert!(s1.is_owner());
+    unsafe { s1.as_ptr().write_volatile(0xAB) };
+    s1.flush().unwrap();
+    drop(s1);
+
+    // The file outlives its owner
+    assert!(dir.join(os_id).is_file());
+    assert!(matches!(
+        ShmemConf::new()
+            .size(4096)
+            .persistent_with_dir(&dir)
+            .os_id(os_id)
+            .create(),
+        Err(ShmemError::MappingIdExists)
+    ));
+
+    let s2 = ShmemConf::new()
+        .size(4096)
+        .persistent_with_dir(&dir)
+        .os_id(os_id)
+        .open()
+        .unwrap();
+    assert_eq!(s2.len(), 4096);
+    assert_eq!(unsafe { s2.as_ptr().read_volatile() }, 0xAB);
+    drop(s2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn persistent_size_validation() {
+    let dir = test_dir("persistent_size_validation");
+    let os_id = "persistent_size";
+
+    let s1 = ShmemConf::new()
+        .size(8192)
+        .persistent_with_dir(&dir)
+        .os_id(os_id)
+        .create()
+        .unwrap();
+
+    assert!(matches!(
+        ShmemConf::new()
+            .size(4096)
+            .persistent_with_dir(&dir)
+            .os_id(os_id)
+            .open(),
+        Err(ShmemError::MapSizeMismatch(8192))
+    ));
+    // Without a size, any existing file can be opened
+    assert!(ShmemConf::new()
+        .persistent_with_dir(&dir)
+        .os_id(os_id)
+        .open()
+        .is_ok());
+
+    drop(s1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn flush_range() {
+    let s = ShmemConf::new().size(3 * 4096).create().unwrap();
+
+    s.flush().unwrap();
+    s.flush_async().unwrap();
+    // Unaligned ranges are fine
+    s.flush_range(5000, 100).unwrap();
+    s.flush_async_range(1, 4096).unwrap();
+
+    assert!(matches!(
+        s.flush_range(3 * 4096, 1),
+        Err(ShmemError::InvalidRange)
+    ));
+    assert!(matches!(
+        s.flush_range(1, usize::MAX),
+        Err(ShmemError::InvalidRange)
+    ));
+}
@@ -0,0 +1,112 @@
+#![cfg(not(target_os = "windows"))]
+
+use shared_memory::{Protection, ShmemConf, ShmemError};
+
+#[test]
+fn private_writes_stay_local() {
+    let s1 = ShmemConf::new().size(4096).create().unwrap();
+    let shared = s1.as_ptr();
+    unsafe { shared.write_volatile(1) };
+
+    let mut s2 = ShmemConf::new()
+        .os_id(s1.get_os_id())
+        .open_private()
+        .unwrap();
+    let private = s2.as_ptr();
+    assert!(!s2.is_owner());
+    assert_eq!(s2.len(), s1.len());
+    assert_eq!(unsafe { private.read_volatile() }, 1);
+
+    // Writes to the private view are not visible to anyone else
+    unsafe { private.write_volatile(2) };
+    assert_eq!(unsafe { shared.read_volatile() }, 1);
+    let s3 = ShmemConf::new().os_id(s1.get_os_id()).open().unwrap();
+    assert_eq!(unsafe { s3.as_ptr().read_volatile() }, 1);
+
+    // Resyncing discards our changes and picks up the shared ones
+    unsafe { shared.write_volatile(3) };
+    s2.resync().unwrap();
+    assert_eq!(unsafe { private.read_volatile() }, 3);
+    assert_eq!(s2.as_ptr(), private);
+}
+
+#[test]
+fn private_tmpfs() {
+    let os_id = "test_private_tmpfs";
+    let s1 = ShmemConf::new()
+        .size(4096)
+        .use_tmpfs_with_dir("/tmp")
+        .os_id(os_id)
+        .create()
+        .unwrap();
+    unsafe { s1.as_ptr().write_volatile(1) };
+
+    let s2 = ShmemConf::new()
+        .use_tmpfs_with_dir("/tmp")
+        .os_id(os_id)
+        .open_private()
+        .unwrap();
+    assert_eq!(unsafe { s2.as_ptr().read_volatile() }, 1);
+
+    unsafe { s2.as_ptr().write_volatile(2) };
+    assert_eq!(unsafe { s1.as_ptr().read_volatile() }, 1);
+}
+
+#[test]
+fn resync_requires_private() {
+    let mut s = ShmemConf::new().size(4096).create().unwrap();
+    assert!(matches!(s.resync(), Err(ShmemError::NotPrivate)));
+}
+
+#[test]
+fn private_rejects_shared_ownership() {
+    let s = ShmemConf::new()
+        .size(4096)
+        .shared_ownership(true)
+        .create()
+        .unwrap();
+    assert!(matches!(
+        ShmemConf::new()
+            .os_id(s.get_os_id())
+            .shared_ownership(true)
+            .open_private(),
+        Err(ShmemError::Unsupported)
+    ));
+    assert!(matches!(
+        ShmemConf::new()
+            .os_id(s.get_os_id())
+            .owner_election(true)
+            .open_private(),
+        Err(ShmemError::Unsupported)
+    ));
+}
+
+#[test]
+fn resync_keeps_protections() {
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+    let s1 = ShmemConf::new().size(2 * page).create().unwrap();
+    let mut s2 = ShmemConf::new()
+        .os_id(s1.get_os_id())
+        .open_private()
+        .unwrap();
+    s2.protect(0..page, Protection::ReadOnly).unwrap();
+    s2.resync().unwrap();
+
+    // The first page is still read-only, the second one is writable
+    assert!(matches!(
+        s2.write_at(0, 1u8),
+        Err(ShmemError::RangeProtected)
+    ));
+    s2.write_at(page, 1u8).unwrap();
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        unsafe {
+            s2.as_ptr().write_volatile(1);
+            libc::_exit(0);
+        }
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFSIGNALED(status));
+}
@@ -0,0 +1,124 @@
+#![cfg(not(target_os = "windows"))]
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use shared_memory::{Protection, ShmemConf, ShmemError};
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Runs `f` in a forked child and returns the signal that killed it, if any
+fn signal_in_child(f: impl FnOnce()) -> Option<i32> {
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        f();
+        unsafe { libc::_exit(0) };
+    }
+
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    if libc::WIFSIGNALED(status) {
+        Some(libc::WTERMSIG(status))
+    } else {
+        None
+    }
+}
+
+#[test]
+fn protect_read_only() {
+    let page = page_size();
+    let mut s = ShmemConf::new().size(3 * page).create().unwrap();
+    let ptr = s.as_ptr();
+
+    s.protect(page..2 * page, Protection::ReadOnly).unwrap();
+
+    // Reads are fine, writes fault
+    assert_eq!(
+        signal_in_child(|| unsafe {
+            ptr.add(page).read_volatile();
+            ptr.write_volatile(1);
+            ptr.add(2 * page).write_volatile(1);
+        }),
+        None
+    );
+    assert_eq!(
+        signal_in_child(|| unsafe { ptr.add(page).write_volatile(1) }),
+        Some(libc::SIGSEGV)
+    );
+
+    // The mapping can still be read as a whole but not written
+    assert_eq!(unsafe { s.as_slice() }.len(), s.len());
+    assert!(catch_unwind(AssertUnwindSafe(|| unsafe {
+        s.as_slice_mut();
+    }))
+    .is_err());
+    assert!(matches!(
+        unsafe { s.try_as_slice_mut() },
+        Err(ShmemError::RangeProtected)
+    ));
+    assert_eq!(unsafe { s.try_as_slice() }.unwrap().len(), s.len());
+
+    s.protect(0..3 * page, Protection::ReadWrite).unwrap();
+    assert_eq!(unsafe { s.as_slice_mut() }.len(), s.len());
+}
+
+#[test]
+fn protect_none() {
+    let page = page_size();
+    let mut s = ShmemConf::new().size(2 * page).create().unwrap();
+    let ptr = s.as_ptr();
+
+    s.protect(0..page, Protection::None).unwrap();
+    assert_eq!(
+        signal_in_child(|| unsafe {
+            ptr.read_volatile();
+        }),
+        Some(libc::SIGSEGV)
+    );
+    assert!(catch_unwind(AssertUnwindSafe(|| unsafe {
+        s.as_slice();
+    }))
+    .is_err());
+    assert!(matches!(
+        unsafe { s.try_as_slice() },
+        Err(ShmemError::RangeProtected)
+    ));
+
+    // Only part of the protected range is restored
+    s.protect(0..page, Protection::ReadOnly).unwrap();
+    assert_eq!(unsafe { s.as_slice() }.len(), s.len());
+}
+
+#[test]
+fn protect_validation() {
+    let page = page_size();
+    let mut s = ShmemConf::new().size(page + 100).create().unwrap();
+
+    assert!(matches!(
+        s.protect(1..page, Protection::ReadOnly),
+        Err(ShmemError::UnalignedRange)
+    ));
+    assert!(matches!(
+        s.protect(0..100, Protection::ReadOnly),
+        Err(ShmemError::UnalignedRange)
+    ));
+    assert!(matches!(
+        s.protect(0..2 * page, Protection::ReadOnly),
+        Err(ShmemError::InvalidRange)
+    ));
+    // The end of the mapping does not need to be aligned
+    s.protect(page..page + 100, Protection::ReadOnly).unwrap();
+
+    // The user visible mapping starts after the header, which isn't page aligned
+    let mut s = ShmemConf::new()
+        .size(2 * page)
+        .shared_ownership(true)
+        .create()
+        .unwrap();
+    assert!(matches!(
+        s.protect(0..s.len(), Protection::ReadOnly),
+        Err(ShmemError::UnalignedRange)
+    ));
+}
@@ -0,0 +1,207 @@
+use std::cell::Cell;
+use std::path::PathBuf;
+
+use shared_memory::{Protection, ShmemConf, ShmemError, SnapshotConf, SnapshotInfo, SnapshotLock};
+
+/// Returns a snapshot path in a directory private to this test
+fn snap_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(format!("{name}.snap"))
+}
+
+fn fill(s: &mut shared_memory::Shmem) {
+    unsafe {
+        for (i, b) in s.as_slice_mut().iter_mut().enumerate() {
+            *b = i as u8;
+        }
+    }
+}
+
+#[test]
+fn snapshot_restore() {
+    let path = &snap_path("snapshot_restore");
+    let mut s1 = ShmemConf::new().size(8192).create().unwrap();
+    fill(&mut s1);
+
+    s1.snapshot_to(path).unwrap();
+    let s2 = ShmemConf::new().create_from_snapshot(path).unwrap();
+
+    assert!(s2.is_owner());
+    assert_ne!(s1.get_os_id(), s2.get_os_id());
+    assert_eq!(s1.len(), s2.len());
+    unsafe {
+        assert_eq!(s1.as_slice(), s2.as_slice());
+    }
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn snapshot_checksum() {
+    let path = &snap_path("snapshot_checksum");
+    let mut s1 = ShmemConf::new().size(4096).create().unwrap();
+    fill(&mut s1);
+
+    let conf = SnapshotConf::new().checksum(true);
+    s1.snapshot_to_with(path, &conf).unwrap();
+    let s2 = ShmemConf::new()
+        .create_from_snapshot_with(path, &conf)
+        .unwrap();
+    unsafe {
+        assert_eq!(s1.as_slice(), s2.as_slice());
+    }
+
+    // Corrupt the last byte of the data
+    let mut contents = std::fs::read(path).unwrap();
+    *contents.last_mut().unwrap() ^= 0xFF;
+    std::fs::write(path, contents).unwrap();
+    assert!(matches!(
+        ShmemConf::new().create_from_snapshot(path),
+        Err(ShmemError::SnapshotChecksumMismatch)
+    ));
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn snapshot_checksum_concurrent_writer() {
+    let path = &snap_path("snapshot_checksum_concurrent_writer");
+    let s = ShmemConf::new().size(4 << 20).create().unwrap();
+    let conf = SnapshotConf::new().checksum(true);
+
+    // The checksum must describe the bytes in the file even if the mapping changes during the copy
+    let done = std::sync::atomic::AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let mut n = 0u64;
+            while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                let offset = (n as usize * 4096 + 8 * (n as usize % 7)) % s.len();
+                s.atomic_u64_at(offset)
+                    .unwrap()
+                    .store(n, std::sync::atomic::Ordering::Relaxed);
+                n += 1;
+            }
+        });
+        let res = (0..3).try_for_each(|_| {
+            s.snapshot_to_with(path, &conf)?;
+            ShmemConf::new()
+                .create_from_snapshot_with(path, &conf)
+                .map(drop)
+        });
+        done.store(true, std::sync::atomic::Ordering::Relaxed);
+        res.unwrap();
+    });
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn snapshot_layout_id() {
+    let path = &snap_path("snapshot_layout_id");
+    let s1 = ShmemConf::new().size(4096).create().unwrap();
+
+    s1.snapshot_to_with(path, &SnapshotConf::new().layout_id(42))
+        .unwrap();
+    assert!(ShmemConf::new()
+        .create_from_snapshot_with(path, &SnapshotConf::new().layout_id(42))
+        .is_ok());
+    assert!(matches!(
+        ShmemConf::new().create_from_snapshot_with(path, &SnapshotConf::new().layout_id(7)),
+        Err(ShmemError::SnapshotLayoutMismatch)
+    ));
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn snapshot_lock() {
+    struct CountingLock {
+        locked: Cell<u32>,
+        unlocked: Cell<u32>,
+    }
+    impl SnapshotLock for CountingLock {
+        fn lock(&self) -> Result<(), ShmemError> {
+            self.locked.set(self.locked.get() + 1);
+            Ok(())
+        }
+        fn unlock(&self) {
+            self.unlocked.set(self.unlocked.get() + 1);
+        }
+    }
+
+    let path = &snap_path("snapshot_lock");
+    let lock = CountingLock {
+        locked: Cell::new(0),
+        unlocked: Cell::new(0),
+    };
+    let s1 = ShmemConf::new().size(4096).create().unwrap();
+    s1.snapshot_to_with(path, &SnapshotConf::new().lock(&lock))
+        .unwrap();
+
+    assert_eq!(lock.locked.get(), 1);
+    assert_eq!(lock.unlocked.get(), 1);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn snapshot_invalid_file() {
+    let path = &snap_path("snapshot_invalid");
+    std::fs::write(path, vec![0u8; 128]).unwrap();
+    assert!(matches!(
+        ShmemConf::new().create_from_snapshot(path),
+        Err(ShmemError::SnapshotInvalid)
+    ));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn snapshot_info() {
+    let path = &snap_path("snapshot_info");
+    let s1 = ShmemConf::new().size(4096).create().unwrap();
+    s1.snapshot_to_with(path, &SnapshotConf::new().checksum(true).layout_id(42))
+        .unwrap();
+
+    let info = SnapshotInfo::read(path).unwrap();
+    assert_eq!(info.os_id, s1.get_os_id());
+    assert_eq!(info.len, s1.len());
+    assert_eq!(info.layout_id, 42);
+    assert!(info.checksum);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn snapshot_failure_leaves_no_file() {
+    struct FailingLock;
+    impl SnapshotLock for FailingLock {
+        fn lock(&self) -> Result<(), ShmemError> {
+            Err(ShmemError::Unsupported)
+        }
+        fn unlock(&self) {}
+    }
+
+    let path = &snap_path("snapshot_failure");
+    let dir = path.parent().unwrap();
+    let mut s1 = ShmemConf::new().size(4096).create().unwrap();
+
+    assert!(matches!(
+        s1.snapshot_to_with(path, &SnapshotConf::new().lock(&FailingLock)),
+        Err(ShmemError::Unsupported)
+    ));
+    assert_eq!(std::fs::read_dir(dir).unwrap().count(), 0);
+
+    // A protected mapping is an error rather than a panic, and an existing snapshot is left intact
+    s1.snapshot_to(path).unwrap();
+    let before = std::fs::read(path).unwrap();
+    s1.protect(0..4096, Protection::None).unwrap();
+    assert!(matches!(
+        s1.snapshot_to(path),
+        Err(ShmemError::RangeProtected)
+    ));
+    assert_eq!(std::fs::read(path).unwrap(), before);
+    assert_eq!(std::fs::read_dir(dir).unwrap().count(), 1);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
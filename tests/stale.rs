@@ -0,0 +1,148 @@
+#![cfg(not(target_os = "windows"))]
+
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use shared_memory::{PosixShmBackend, ShmemBackend, ShmemConf, ShmemError, TmpfsBackend};
+
+#[test]
+fn stale_after_unlink() {
+    let s1 = ShmemConf::new().size(4096).create().unwrap();
+    let s2 = ShmemConf::new().os_id(s1.get_os_id()).open().unwrap();
+    assert!(!s1.is_stale().unwrap());
+    assert!(!s2.is_stale().unwrap());
+
+    PosixShmBackend.unlink(s1.get_os_id()).unwrap();
+    assert!(s1.is_stale().unwrap());
+    assert!(s2.is_stale().unwrap());
+}
+
+#[test]
+fn stale_after_replace() {
+    let s1 = ShmemConf::new().size(4096).create().unwrap();
+    let os_id = s1.get_os_id().to_string();
+    let s2 = ShmemConf::new().os_id(&os_id).open().unwrap();
+
+    // The owner goes away and someone else reuses the os_id
+    drop(s1);
+    let s3 = ShmemConf::new().size(4096).os_id(&os_id).create().unwrap();
+    assert!(s2.is_stale().unwrap());
+    assert!(!s3.is_stale().unwrap());
+}
+
+#[test]
+fn stale_tmpfs() {
+    let dir = std::env::temp_dir();
+    let s1 = ShmemConf::new()
+        .size(4096)
+        .use_tmpfs_with_dir(&dir)
+        .create()
+        .unwrap();
+    assert!(!s1.is_stale().unwrap());
+
+    std::fs::remove_file(s1.get_tmpfs_file_path().unwrap()).unwrap();
+    assert!(s1.is_stale().unwrap());
+}
+
+#[test]
+fn stale_keeps_election_locks() {
+    let dir = std::env::temp_dir();
+    let os_id = format!("shmem_stale_locks_{}", std::process::id());
+    let s = ShmemConf::new()
+        .size(4096)
+        .os_id(&os_id)
+        .use_tmpfs_with_dir(&dir)
+        .owner_election(true)
+        .create()
+        .unwrap();
+    let path = s.get_tmpfs_file_path().unwrap();
+    let path = path.to_str().unwrap();
+
+    // Inspecting the object must not release the lock telling the owner is alive
+    assert!(!s.is_stale().unwrap());
+    assert_eq!(TmpfsBackend.info(path).unwrap().size, s.len() + 64);
+    assert!(matches!(
+        TmpfsBackend.stat(&format!("{path}.missing")),
+        Err(ShmemError::MapOpenFailed(e)) if e == libc::ENOENT as u32
+    ));
+
+    // Locks held by our own process are invisible to us outside of Linux, check from a child
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        let code = match ShmemConf::new()
+            .os_id(&os_id)
+            .use_tmpfs_with_dir(&dir)
+            .owner_election(true)
+            .open()
+        {
+            Ok(mut other) => match other.elect_owner() {
+                Ok(false) => 0,
+                _ => 2,
+            },
+            Err(_) => 1,
+        };
+        unsafe { libc::_exit(code) };
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert_eq!(libc::WEXITSTATUS(status), 0);
+}
+
+#[test]
+fn stale_sysv() {
+    let key = ((std::process::id() as i32) << 8 | 0x40) as libc::key_t;
+    let s1 = ShmemConf::new().size(4096).sysv_key(key).create().unwrap();
+    let s2 = ShmemConf::new().sysv_key(key).open().unwrap();
+    assert!(!s2.is_stale().unwrap());
+
+    drop(s1);
+    assert!(s2.is_stale().unwrap());
+}
+
+#[test]
+fn watch_stale() {
+    let s = ShmemConf::new().size(4096).create().unwrap();
+    let (tx, rx) = channel();
+    let _watcher = s
+        .watch_stale(Duration::from_millis(10), move || tx.send(()).unwrap())
+        .unwrap();
+    assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+    PosixShmBackend.unlink(s.get_os_id()).unwrap();
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+}
+
+#[test]
+fn watch_stale_stops_on_drop() {
+    let s = ShmemConf::new().size(4096).create().unwrap();
+    let (tx, rx) = channel();
+    let watcher = s
+        .watch_stale(Duration::from_millis(10), move || tx.send(()).unwrap())
+        .unwrap();
+    drop(watcher);
+
+    // The callback was dropped along with the thread
+    assert!(rx.recv().is_err());
+}
+
+#[test]
+fn stale_unsupported() {
+    let s = ShmemConf::new().size(4096).anonymous().unwrap();
+    assert!(matches!(s.is_stale(), Err(ShmemError::Unsupported)));
+    assert!(matches!(
+        s.watch_stale(Duration::from_millis(10), || {}).map(|_| ()),
+        Err(ShmemError::Unsupported)
+    ));
+
+    // memfds have no name to check against
+    #[cfg(target_os = "linux")]
+    {
+        let s = ShmemConf::new()
+            .size(4096)
+            .backend(shared_memory::MemfdBackend)
+            .create()
+            .unwrap();
+        assert!(matches!(s.is_stale(), Err(ShmemError::Unsupported)));
+    }
+}
@@ -0,0 +1,123 @@
+#![cfg(not(target_os = "windows"))]
+
+use std::ptr::null_mut;
+
+use shared_memory::{ShmemConf, ShmemError};
+
+/// Returns a key that is unlikely to be used by anyone else
+fn test_key(n: i32) -> libc::key_t {
+    ((std::process::id() as i32) << 8 | n) as libc::key_t
+}
+
+#[test]
+fn sysv_create_open() {
+    let key = test_key(1);
+    let s1 = ShmemConf::new().size(4096).sysv_key(key).create().unwrap();
+    assert!(s1.is_owner());
+    assert_eq!(s1.len(), 4096);
+    assert_eq!(s1.get_os_id(), format!("{key:#010x}"));
+    assert!(matches!(
+        ShmemConf::new().size(4096).sysv_key(key).create(),
+        Err(ShmemError::MappingIdExists)
+    ));
+
+    let s2 = ShmemConf::new().sysv_key(key).open().unwrap();
+    assert!(!s2.is_owner());
+    assert_eq!(s2.len(), 4096);
+    unsafe { s1.as_ptr().write_volatile(0xAB) };
+    assert_eq!(unsafe { s2.as_ptr().read_volatile() }, 0xAB);
+
+    assert_eq!(s1.attach_count().unwrap(), 2);
+    drop(s2);
+    assert_eq!(s1.attach_count().unwrap(), 1);
+
+    // The owner removes the segment
+    drop(s1);
+    assert!(matches!(
+        ShmemConf::new().sysv_key(key).open(),
+        Err(ShmemError::MapOpenFailed(_))
+    ));
+}
+
+#[test]
+fn sysv_attach_foreign_segment() {
+    // Segment published by a program that does not use this crate
+    let key = test_key(2);
+    let id = unsafe { libc::shmget(key, 100, libc::IPC_CREAT | libc::IPC_EXCL | 0o600) };
+    assert!(id >= 0);
+    let ptr = unsafe { libc::shmat(id, null_mut(), 0) } as *mut u8;
+    unsafe { ptr.add(99).write_volatile(0xCD) };
+
+    let s = ShmemConf::new().sysv_key(key).open().unwrap();
+    assert_eq!(s.len(), 100);
+    assert_eq!(unsafe { s.as_ptr().add(99).read_volatile() }, 0xCD);
+    assert_eq!(s.attach_count().unwrap(), 2);
+    drop(s);
+
+    unsafe {
+        libc::shmdt(ptr as *const _);
+        libc::shmctl(id, libc::IPC_RMID, null_mut());
+    }
+}
+
+#[test]
+fn sysv_unsupported() {
+    let s = ShmemConf::new().size(4096).create().unwrap();
+    assert!(matches!(s.attach_count(), Err(ShmemError::Unsupported)));
+
+    assert!(matches!(
+        ShmemConf::new()
+            .size(4096)
+            .sysv_key(test_key(3))
+            .guard_pages(1)
+            .create(),
+        Err(ShmemError::Unsupported)
+    ));
+    assert!(matches!(
+        ShmemConf::new()
+            .size(4096)
+            .sysv_key(test_key(3))
+            .populate(true)
+            .create(),
+        Err(ShmemError::Unsupported)
+    ));
+}
+
+#[test]
+fn sysv_rejects_owner_election() {
+    assert!(matches!(
+        ShmemConf::new()
+            .size(4096)
+            .sysv_key(test_key(4))
+            .owner_election(true)
+            .create(),
+        Err(ShmemError::Unsupported)
+    ));
+    assert!(matches!(
+        ShmemConf::new()
+            .sysv_key(test_key(4))
+            .owner_election(true)
+            .open(),
+        Err(ShmemError::Unsupported)
+    ));
+}
+
+#[test]
+fn sysv_ignores_flink() {
+    let key = test_key(5);
+    let flink = std::env::temp_dir().join(format!("sysv_flink_{}", std::process::id()));
+    std::fs::write(&flink, "not ours").unwrap();
+
+    // The existing file is neither an error nor overwritten, nor deleted by the owner
+    let s = ShmemConf::new()
+        .size(4096)
+        .sysv_key(key)
+        .flink(&flink)
+        .create()
+        .unwrap();
+    assert!(s.get_flink_path().is_none());
+    drop(s);
+    assert_eq!(std::fs::read_to_string(&flink).unwrap(), "not ours");
+
+    std::fs::remove_file(&flink).unwrap();
+}
@@ -174,6 +174,43 @@ fn tmpfs_open_flink() {
     drop(s2);
 }
 
+#[test]
+fn tmpfs_open_flink_relative_dir() {
+    let flink = Path::new("tmpfs_open_flink_relative_test");
+    let dir = Path::new("tmpfs_relative_dir_test");
+    std::fs::create_dir_all(dir).unwrap();
+
+    let mut s1 = ShmemConf::new()
+        .flink(flink)
+        .size(4090)
+        .use_tmpfs_with_dir(dir)
+        .create()
+        .unwrap();
+    unsafe { s1.as_slice_mut()[0] = 0xAB };
+
+    // The same directory spelled differently by the openers
+    let absolute = std::env::current_dir().unwrap().join(dir);
+    for base_dir in [Path::new("./tmpfs_relative_dir_test/"), absolute.as_path()] {
+        let s2 = ShmemConf::new()
+            .flink(flink)
+            .use_tmpfs_with_dir(base_dir)
+            .open()
+            .unwrap();
+        assert!(!s2.is_owner());
+        assert_eq!(unsafe { s2.as_slice()[0] }, 0xAB);
+    }
+
+    // Another directory is still rejected
+    assert!(ShmemConf::new()
+        .flink(flink)
+        .use_tmpfs_with_dir("/tmp")
+        .open()
+        .is_err());
+
+    drop(s1);
+    std::fs::remove_dir(dir).unwrap();
+}
+
 #[test]
 fn tmpfs_share_data() {
     let os_id = "test_tmpfs_share_data";